@@ -0,0 +1,48 @@
+//! The cost of processing a request, with and without deferring the creation of the root span.
+//!
+//! Run it with `cargo bench --bench lazy_root_span`.
+use actix_web::{test, web, App, HttpResponse};
+use std::time::{Duration, Instant};
+use tracing_actix_web::TracingLogger;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+
+const WARM_UP: u32 = 1_000;
+const REQUESTS: u32 = 100_000;
+
+fn main() {
+    // The root span is an `INFO` span: it is disabled at `WARN`.
+    for level in [LevelFilter::INFO, LevelFilter::WARN] {
+        let subscriber = tracing_subscriber::registry().with(level);
+        let _guard = tracing::subscriber::set_default(subscriber);
+        for lazy in [false, true] {
+            let elapsed = measure(lazy);
+            println!(
+                "max level: {:<5} lazy: {:<5} {:?}/request",
+                level.to_string(),
+                lazy,
+                elapsed / REQUESTS
+            );
+        }
+    }
+}
+
+/// The time it takes to process `REQUESTS` sequential requests.
+fn measure(lazy: bool) -> Duration {
+    actix_web::rt::System::new().block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default().lazy_root_span(lazy))
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        let mut elapsed = Duration::ZERO;
+        for i in 0..WARM_UP + REQUESTS {
+            let started_at = Instant::now();
+            let response = test::call_service(&app, test::TestRequest::get().to_request()).await;
+            test::read_body(response).await;
+            if i >= WARM_UP {
+                elapsed += started_at.elapsed();
+            }
+        }
+        elapsed
+    })
+}
@@ -0,0 +1,83 @@
+use actix_web::http::header::{HeaderMap, ACCEPT};
+
+/// Where [`TracingLogger`] looks for the version of your API targeted by a request, recorded
+/// as `http.api_version`.
+///
+/// Versions are normalized to a lowercase `v` followed by the version number (e.g. `v2` or
+/// `v2.1`), regardless of the way they were spelled by the client.
+///
+/// ```rust
+/// use tracing_actix_web::{ApiVersionSource, TracingLogger};
+///
+/// // `/v2/users/42` is recorded as `v2`
+/// let logger = TracingLogger::default().record_api_version(ApiVersionSource::PathPrefix);
+/// ```
+///
+/// [`TracingLogger`]: crate::TracingLogger
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ApiVersionSource {
+    /// The first segment of the path, if it is a version (e.g. `/v2/users` or `/V2.1/users`).
+    PathPrefix,
+    /// The `Accept` header, either as a vendor media type (e.g. `application/vnd.acme.v2+json`)
+    /// or as a `version` parameter (e.g. `application/json; version=2`).
+    ///
+    /// The first media type carrying a version wins.
+    AcceptHeader,
+}
+
+impl ApiVersionSource {
+    /// The normalized version targeted by a request, if it can be determined.
+    pub(crate) fn version(&self, path: &str, headers: &HeaderMap) -> Option<String> {
+        match self {
+            ApiVersionSource::PathPrefix => {
+                let segment = path.split('/').find(|segment| !segment.is_empty())?;
+                normalize(segment.strip_prefix(|c| c == 'v' || c == 'V')?)
+            }
+            ApiVersionSource::AcceptHeader => headers
+                .get_all(ACCEPT)
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .find_map(accept_version),
+        }
+    }
+}
+
+/// The version carried by a single media range of an `Accept` header.
+fn accept_version(media_range: &str) -> Option<String> {
+    let mut parts = media_range.split(';');
+    let media_type = parts.next()?.trim();
+    let from_parameter = parts.find_map(|parameter| {
+        let (name, value) = parameter.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("version") {
+            return None;
+        }
+        let value = value.trim().trim_matches('"');
+        normalize(
+            value
+                .strip_prefix(|c| c == 'v' || c == 'V')
+                .unwrap_or(value),
+        )
+    });
+    if from_parameter.is_some() {
+        return from_parameter;
+    }
+    // e.g. `vnd.acme.v2+json`
+    let (_, subtype) = media_type.split_once('/')?;
+    let subtype = subtype.split('+').next()?;
+    subtype
+        .split('.')
+        .filter_map(|segment| segment.strip_prefix(|c| c == 'v' || c == 'V'))
+        .find_map(normalize)
+}
+
+/// `v<number>`, if `number` is made of one or more dot-separated groups of digits.
+fn normalize(number: &str) -> Option<String> {
+    let is_valid = number
+        .split('.')
+        .all(|group| !group.is_empty() && group.bytes().all(|b| b.is_ascii_digit()));
+    if is_valid {
+        Some(format!("v{}", number))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,239 @@
+use actix_web::dev::Extensions;
+use std::any::Any;
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+/// Capture connection-level metadata that [`TracingLogger`] uses to enrich the root span.
+///
+/// Register `on_connect` with [`HttpServer::on_connect`]:
+///
+/// ```rust
+/// use actix_web::{App, HttpServer};
+/// use tracing_actix_web::TracingLogger;
+///
+/// let server = HttpServer::new(|| App::new().wrap(TracingLogger::default()))
+///     .on_connect(tracing_actix_web::on_connect);
+/// ```
+///
+/// If you need to set up your own connection-level data, call `on_connect` from your callback:
+///
+/// ```rust
+/// use actix_web::dev::Extensions;
+/// use std::any::Any;
+///
+/// fn on_connect(connection: &dyn Any, data: &mut Extensions) {
+///     tracing_actix_web::on_connect(connection, data);
+///     // [...]
+/// }
+/// ```
+///
+/// Check out [`ConnectionMetadata`] for the list of captured properties.
+///
+/// [`TracingLogger`]: crate::TracingLogger
+/// [`HttpServer::on_connect`]: actix_web::HttpServer::on_connect
+pub fn on_connect(_connection: &dyn Any, data: &mut Extensions) {
+    data.insert(ConnectionMetadata::new());
+}
+
+/// Connection-level metadata captured by [`on_connect`].
+///
+/// It is stored as connection data, shared by all the requests served over the same connection.
+///
+/// [`DefaultRootSpanBuilder`] uses it to record:
+///
+/// - whether the request is served over a connection that was already used to serve previous
+///   requests (`net.connection.reused`);
+/// - the time, in milliseconds, between the connection being handed over to a worker and the start
+///   of the processing of the first request on the connection (`net.time_to_first_request_ms`);
+/// - the time, in milliseconds, between the connection being accepted and the response to the
+///   first request on the connection being complete (`net.full_duration_ms`), if
+///   [enabled](crate::TracingLogger::record_full_duration) - see [Accept time](#accept-time).
+///
+/// # Time to first request
+///
+/// `net.time_to_first_request_ms` is not a queueing time. [`on_connect`] is invoked by the worker
+/// that is going to serve the connection, after the TLS handshake (if any) has completed: the time
+/// the connection spent waiting to be picked up by a worker is not included. Most of what is
+/// included is the time the client took to send the head of its first request - a slow or idle
+/// client shows up as a large value even if your server is not loaded at all.
+/// It is not recorded for requests served over a reused (keep-alive) connection.
+///
+/// Measuring queueing time requires the instant a connection was accepted by the operating system,
+/// which `actix-web` does not expose - check out [Accept time](#accept-time).
+///
+/// # Accept time
+///
+/// For the same reason, `tracing-actix-web` can't capture by itself the instant a connection was
+/// accepted, which `net.full_duration_ms` is measured from. By default, it is measured from the
+/// instant [`on_connect`] was invoked, moved back by the duration of the TLS handshake if
+/// [`TlsConnectionInfo`] knows it: the time the connection spent waiting to be picked up by a
+/// worker is not included.
+///
+/// For a precise measurement, take note of the instant each connection was accepted in the layer
+/// that accepts them - e.g. a wrapper around your TCP or TLS acceptor that stamps each stream
+/// before the TLS handshake. Make the timestamp available to your connection callback and set
+/// it using [`set_accepted_at`](Self::set_accepted_at):
+///
+/// ```rust
+/// use actix_web::dev::Extensions;
+/// use std::any::Any;
+/// use std::time::Instant;
+/// use tracing_actix_web::ConnectionMetadata;
+///
+/// /// The stream type produced by your acceptor.
+/// struct TimestampedStream<S> {
+///     stream: S,
+///     accepted_at: Instant,
+/// }
+///
+/// fn on_connect(connection: &dyn Any, data: &mut Extensions) {
+///     tracing_actix_web::on_connect(connection, data);
+///     let stream = connection.downcast_ref::<TimestampedStream<actix_web::rt::net::TcpStream>>();
+///     if let Some(stream) = stream {
+///         if let Some(metadata) = data.get_mut::<ConnectionMetadata>() {
+///             metadata.set_accepted_at(stream.accepted_at);
+///         }
+///     }
+/// }
+/// ```
+///
+/// # HTTP/2 server push
+///
+/// There is no field recording whether a connection used HTTP/2 server push: `actix-web` never
+/// pushes. Its HTTP/2 dispatcher keeps the `h2` response handle - the only way to send a
+/// `PUSH_PROMISE` frame - to itself and doesn't expose it to handlers, middlewares or
+/// connection callbacks. Pushes can't be attempted, so there is nothing to observe.
+///
+/// [`DefaultRootSpanBuilder`]: crate::DefaultRootSpanBuilder
+#[derive(Debug)]
+pub struct ConnectionMetadata {
+    established_at: Instant,
+    accepted_at: Option<Instant>,
+    served_requests: Cell<u64>,
+}
+
+impl ConnectionMetadata {
+    fn new() -> Self {
+        Self {
+            established_at: Instant::now(),
+            accepted_at: None,
+            served_requests: Cell::new(0),
+        }
+    }
+
+    /// The instant the connection was handed over to the worker serving it.
+    pub fn established_at(&self) -> Instant {
+        self.established_at
+    }
+
+    /// Set the instant the connection was accepted, as measured by the layer accepting connections.
+    pub fn set_accepted_at(&mut self, accepted_at: Instant) {
+        self.accepted_at = Some(accepted_at);
+    }
+
+    /// The instant the connection was accepted, if known.
+    pub fn accepted_at(&self) -> Option<Instant> {
+        self.accepted_at
+    }
+
+    /// The instant `net.full_duration_ms` is measured from: the [accept time](Self::accepted_at)
+    /// if known, the instant the connection was established minus the TLS handshake otherwise.
+    pub(crate) fn accepted_since(&self, tls: Option<&TlsConnectionInfo>) -> Instant {
+        if let Some(accepted_at) = self.accepted_at {
+            return accepted_at;
+        }
+        tls.and_then(TlsConnectionInfo::handshake_duration)
+            .and_then(|handshake| self.established_at.checked_sub(handshake))
+            .unwrap_or(self.established_at)
+    }
+
+    /// The number of requests served over this connection before the in-flight one.
+    pub fn served_requests(&self) -> u64 {
+        self.served_requests.get()
+    }
+
+    pub(crate) fn on_request(&self) {
+        self.served_requests.set(self.served_requests.get() + 1);
+    }
+}
+
+/// TLS-level metadata about a connection.
+///
+/// `tracing-actix-web` can't inspect the TLS session of a connection by itself: the TLS stream type
+/// depends on the TLS backend you are using (`rustls`, `openssl`, etc.).
+/// Use your own connection callback to store a `TlsConnectionInfo` as connection data -
+/// [`DefaultRootSpanBuilder`] will then record:
+///
+/// - whether the TLS session was resumed instead of going through a full handshake (`tls.resumed`);
+/// - how long the TLS handshake took (`tls.handshake.duration_ms`), in milliseconds.
+///
+/// Fields are skipped if `TlsConnectionInfo` is missing or the corresponding value was not set.
+///
+/// # Handshake duration
+///
+/// The handshake has already completed when your connection callback is invoked: its duration
+/// must be measured by the layer that accepts TLS connections - e.g. a wrapper around the TLS
+/// acceptor of your backend that takes note of when the TCP connection was accepted and when the
+/// handshake completed. Make the measurement available to your connection callback (e.g. by
+/// storing it alongside the TLS stream) and set it using
+/// [`with_handshake_duration`](TlsConnectionInfo::with_handshake_duration).
+///
+/// # Example
+///
+/// Using `rustls` 0.23:
+///
+/// ```rust,ignore
+/// use actix_tls::accept::rustls_0_23::TlsStream;
+/// use actix_web::dev::Extensions;
+/// use actix_web::rt::net::TcpStream;
+/// use rustls::HandshakeKind;
+/// use std::any::Any;
+/// use tracing_actix_web::TlsConnectionInfo;
+///
+/// fn on_connect(connection: &dyn Any, data: &mut Extensions) {
+///     tracing_actix_web::on_connect(connection, data);
+///     if let Some(stream) = connection.downcast_ref::<TlsStream<TcpStream>>() {
+///         let (_, session) = stream.get_ref();
+///         let resumed = session.handshake_kind() == Some(HandshakeKind::Resumed);
+///         data.insert(TlsConnectionInfo::new().resumed(resumed));
+///     }
+/// }
+/// ```
+///
+/// [`DefaultRootSpanBuilder`]: crate::DefaultRootSpanBuilder
+#[derive(Clone, Debug, Default)]
+pub struct TlsConnectionInfo {
+    resumed: Option<bool>,
+    handshake_duration: Option<Duration>,
+}
+
+impl TlsConnectionInfo {
+    /// Create a `TlsConnectionInfo` with no value set: no TLS field is recorded until you set
+    /// them using [`resumed`](Self::resumed) and
+    /// [`with_handshake_duration`](Self::with_handshake_duration).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether the TLS session was resumed.
+    pub fn resumed(mut self, resumed: bool) -> Self {
+        self.resumed = Some(resumed);
+        self
+    }
+
+    /// Set how long the TLS handshake took.
+    pub fn with_handshake_duration(mut self, duration: Duration) -> Self {
+        self.handshake_duration = Some(duration);
+        self
+    }
+
+    /// Whether the TLS session was resumed, if known.
+    pub fn is_resumed(&self) -> Option<bool> {
+        self.resumed
+    }
+
+    /// How long the TLS handshake took, if known.
+    pub fn handshake_duration(&self) -> Option<Duration> {
+        self.handshake_duration
+    }
+}
@@ -0,0 +1,76 @@
+use actix_web::http::header::ORIGIN;
+use actix_web::HttpMessage;
+
+/// The outcome of the CORS checks performed on the in-flight request.
+///
+/// `tracing-actix-web` does not perform CORS checks: it is the job of a dedicated middleware
+/// (e.g. [`actix-cors`](https://docs.rs/actix-cors)).
+/// The CORS middleware (or a thin wrapper around it) can store its decision in request-local
+/// storage using [`CorsOutcome::record`]: [`DefaultRootSpanBuilder`] records it when the request
+/// has been processed as
+///
+/// - whether the request was allowed (`http.cors.allowed`);
+/// - the value of the `Origin` header, if the request was rejected (`http.cors.origin`).
+///
+/// # Integration with `actix-cors`
+///
+/// `actix-cors` rejects requests returning an error response. Place a thin middleware between
+/// `TracingLogger` and `Cors` to record its decisions:
+///
+/// ```rust,ignore
+/// use actix_cors::{Cors, CorsError};
+/// use actix_web::dev::Service;
+/// use actix_web::App;
+/// use tracing_actix_web::{CorsOutcome, TracingLogger};
+///
+/// let app = App::new()
+///     .wrap(Cors::default())
+///     .wrap_fn(|req, srv| {
+///         let fut = srv.call(req);
+///         async move {
+///             let res = fut.await?;
+///             if res.request().headers().contains_key("Origin") {
+///                 let rejected = res
+///                     .response()
+///                     .error()
+///                     .and_then(|e| e.as_error::<CorsError>())
+///                     .is_some();
+///                 CorsOutcome::record(res.request(), !rejected);
+///             }
+///             Ok(res)
+///         }
+///     })
+///     .wrap(TracingLogger::default());
+/// ```
+///
+/// [`DefaultRootSpanBuilder`]: crate::DefaultRootSpanBuilder
+#[derive(Clone, Debug)]
+pub struct CorsOutcome {
+    allowed: bool,
+    origin: Option<String>,
+}
+
+impl CorsOutcome {
+    /// Store the outcome of the CORS checks for `request` in request-local storage.
+    ///
+    /// The `Origin` header is read from `request`.
+    pub fn record<R: HttpMessage>(request: &R, allowed: bool) {
+        let origin = request
+            .headers()
+            .get(ORIGIN)
+            .map(|origin| String::from_utf8_lossy(origin.as_bytes()).into_owned());
+        request
+            .extensions_mut()
+            .insert(CorsOutcome { allowed, origin });
+    }
+
+    /// Whether the request passed the CORS checks.
+    pub fn allowed(&self) -> bool {
+        self.allowed
+    }
+
+    /// The value of the `Origin` header of the request, if it was set.
+    pub fn origin(&self) -> Option<&str> {
+        self.origin.as_deref()
+    }
+}
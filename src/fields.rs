@@ -0,0 +1,263 @@
+use serde_json::{Map, Value as JsonValue};
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex, PoisonError};
+use tracing::callsite::{Callsite, Identifier};
+use tracing::field::{Field, FieldSet, Value, Visit};
+use tracing::metadata::Kind;
+use tracing::subscriber::Interest;
+use tracing::{Level, Metadata, Span};
+
+thread_local! {
+    // The fields of the request whose root span is currently being populated on this thread.
+    static CURRENT_FIELDS: RefCell<Option<RecordedFields>> = const { RefCell::new(None) };
+}
+
+/// A copy of the values recorded on the root span of a request, keyed by field name.
+///
+/// It is only kept if [`WideEventRootSpanBuilder`](crate::WideEventRootSpanBuilder) or the
+/// [JSON access log](crate::TracingLogger::json_access_log) need it: values are copied
+/// when the root span is created by [`root_span!`](crate::root_span!), when `tracing-actix-web`
+/// records them and when they are recorded using [`RootSpan::record`](crate::RootSpan::record).
+/// Values recorded on the span directly (e.g. via `Span::current().record(..)`) are not copied.
+#[derive(Clone, Default)]
+pub(crate) struct RecordedFields(Arc<Mutex<Map<String, JsonValue>>>);
+
+impl RecordedFields {
+    /// The fields of the request whose root span is currently being populated on this thread.
+    pub(crate) fn current() -> Option<RecordedFields> {
+        CURRENT_FIELDS.with(|current| current.borrow().clone())
+    }
+
+    /// Execute `f` with the [current](RecordedFields::current) fields, if any.
+    fn with_current<F: FnOnce(&RecordedFields)>(f: F) {
+        CURRENT_FIELDS.with(|current| {
+            if let Some(fields) = &*current.borrow() {
+                f(fields)
+            }
+        })
+    }
+
+    /// Execute `f` with `fields` set as the [current](RecordedFields::current) fields.
+    pub(crate) fn in_scope<F: FnOnce() -> T, T>(fields: Option<&RecordedFields>, f: F) -> T {
+        struct Reset(Option<RecordedFields>);
+
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                CURRENT_FIELDS.with(|current| *current.borrow_mut() = self.0.take());
+            }
+        }
+
+        let _reset = Reset(CURRENT_FIELDS.with(|current| current.replace(fields.cloned())));
+        f()
+    }
+
+    /// Store a copy of `value` for the field called `name`, replacing the previous one.
+    pub(crate) fn insert(&self, name: &str, value: &dyn Value) {
+        let mut visitor = JsonVisitor(None);
+        value.record(&capture_field(), &mut visitor);
+        // `None` values are not recorded on the span either.
+        if let Some(value) = visitor.0 {
+            self.lock().insert(name.to_owned(), value);
+        }
+    }
+
+    /// The values recorded so far, as a flat JSON object.
+    pub(crate) fn to_json(&self) -> Map<String, JsonValue> {
+        self.lock().clone()
+    }
+
+    /// Record the values stored so far on `span`, emptying `self`.
+    pub(crate) fn replay(&self, span: &Span) {
+        let values = std::mem::take(&mut *self.lock());
+        for (name, value) in values {
+            match value {
+                JsonValue::Bool(value) => record(span, &name, value),
+                JsonValue::String(value) => record(span, &name, value.as_str()),
+                JsonValue::Number(number) => {
+                    if let Some(value) = number.as_u64() {
+                        record(span, &name, value)
+                    } else if let Some(value) = number.as_i64() {
+                        record(span, &name, value)
+                    } else if let Some(value) = number.as_f64() {
+                        record(span, &name, value)
+                    }
+                }
+                // `insert` doesn't produce any other kind of value.
+                value => record(span, &name, value.to_string().as_str()),
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Map<String, JsonValue>> {
+        // A panic while holding the lock can't leave the map in an inconsistent state.
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Record `value` for the field called `name` on `span`, keeping a copy in the
+/// [current](RecordedFields::current) fields.
+pub(crate) fn record<V: Value>(span: &Span, name: &str, value: V) {
+    span.record(name, &value);
+    RecordedFields::with_current(|fields| copy(span, fields, name, &value));
+}
+
+/// Store a copy of `value` in `fields`, if it is recorded by `span`.
+pub(crate) fn copy(span: &Span, fields: &RecordedFields, name: &str, value: &dyn Value) {
+    // Disabled spans and undeclared fields don't record anything.
+    if span.field(name).is_some() {
+        fields.insert(name, value);
+    }
+}
+
+/// Keep a copy of the value of a field set when the root span is created, then hand it over to
+/// `tracing::span!`.
+///
+/// `tracing::span!` doesn't evaluate field values if the span is disabled: nothing is copied in
+/// that case.
+pub(crate) fn capture<V: Value>(name: &str, value: V) -> V {
+    RecordedFields::with_current(|fields| fields.insert(name, &value));
+    value
+}
+
+/// Converts the value of a single field to JSON.
+struct JsonVisitor(Option<JsonValue>);
+
+impl Visit for JsonVisitor {
+    fn record_f64(&mut self, _field: &Field, value: f64) {
+        self.0 = Some(value.into());
+    }
+
+    fn record_i64(&mut self, _field: &Field, value: i64) {
+        self.0 = Some(value.into());
+    }
+
+    fn record_u64(&mut self, _field: &Field, value: u64) {
+        self.0 = Some(value.into());
+    }
+
+    fn record_bool(&mut self, _field: &Field, value: bool) {
+        self.0 = Some(value.into());
+    }
+
+    fn record_str(&mut self, _field: &Field, value: &str) {
+        self.0 = Some(value.into());
+    }
+
+    fn record_debug(&mut self, _field: &Field, value: &dyn Debug) {
+        // `tracing::field::display` values are recorded here, formatted using `Display`.
+        self.0 = Some(format!("{:?}", value).into());
+    }
+}
+
+/// `tracing::Value::record` needs a `Field`: values are recorded against the only field of a
+/// callsite that is never registered, therefore never seen by subscribers.
+struct CaptureCallsite;
+
+static CAPTURE_CALLSITE: CaptureCallsite = CaptureCallsite;
+
+static CAPTURE_METADATA: Metadata<'static> = Metadata::new(
+    "tracing-actix-web field capture",
+    "tracing_actix_web",
+    Level::TRACE,
+    None,
+    None,
+    None,
+    FieldSet::new(&["value"], Identifier(&CAPTURE_CALLSITE)),
+    Kind::EVENT,
+);
+
+impl Callsite for CaptureCallsite {
+    fn set_interest(&self, _interest: Interest) {}
+
+    fn metadata(&self) -> &Metadata<'_> {
+        &CAPTURE_METADATA
+    }
+}
+
+fn capture_field() -> Field {
+    CAPTURE_METADATA
+        .fields()
+        .field("value")
+        .expect("The capture callsite declares a `value` field")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn values_are_converted_to_json() {
+        let fields = RecordedFields::default();
+        fields.insert("status", &200u64);
+        fields.insert("delta", &-3i64);
+        fields.insert("ratio", &0.5f64);
+        fields.insert("reused", &true);
+        fields.insert("method", &"GET");
+        fields.insert("route", &tracing::field::display("/users/{id}"));
+        fields.insert("missing", &None::<&str>);
+
+        assert_eq!(
+            JsonValue::Object(fields.to_json()),
+            json!({
+                "status": 200,
+                "delta": -3,
+                "ratio": 0.5,
+                "reused": true,
+                "method": "GET",
+                "route": "/users/{id}",
+            })
+        );
+    }
+
+    #[test]
+    fn values_are_only_captured_in_scope() {
+        let fields = RecordedFields::default();
+        RecordedFields::in_scope(Some(&fields), || capture("method", "GET"));
+        capture("route", "/");
+
+        assert_eq!(
+            JsonValue::Object(fields.to_json()),
+            json!({ "method": "GET" })
+        );
+        assert!(RecordedFields::current().is_none());
+    }
+
+    #[test]
+    fn replayed_values_keep_their_type() {
+        let buffer = RecordedFields::default();
+        buffer.insert("status", &200u64);
+        buffer.insert("delta", &-3i64);
+        buffer.insert("ratio", &0.5f64);
+        buffer.insert("reused", &true);
+        buffer.insert("method", &"GET");
+
+        let fields = RecordedFields::default();
+        let span = tracing::subscriber::with_default(tracing_subscriber::registry(), || {
+            let span = tracing::info_span!(
+                "HTTP request",
+                status = tracing::field::Empty,
+                delta = tracing::field::Empty,
+                ratio = tracing::field::Empty,
+                reused = tracing::field::Empty,
+                method = tracing::field::Empty,
+            );
+            RecordedFields::in_scope(Some(&fields), || buffer.replay(&span));
+            span
+        });
+
+        assert!(!span.is_disabled());
+        assert!(buffer.to_json().is_empty());
+        assert_eq!(
+            JsonValue::Object(fields.to_json()),
+            json!({
+                "status": 200,
+                "delta": -3,
+                "ratio": 0.5,
+                "reused": true,
+                "method": "GET",
+            })
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use actix_web::guard::{Guard, GuardContext};
+use std::borrow::Cow;
+
+/// The decisions taken by [`TracedGuard`]s while routing the in-flight request.
+///
+/// `GuardDecisions` is stored in request-local storage: [`DefaultRootSpanBuilder`] reads it when
+/// the request has been processed and records
+///
+/// - the names of the guards that accepted the request (`http.route.guard.matched`);
+/// - the names of the guards that rejected the request (`http.route.guard.rejected`).
+///
+/// Names are comma-separated and listed in evaluation order.
+///
+/// You don't need to interact with `GuardDecisions` if you are using [`TracedGuard`].
+/// If you have your own [`Guard`] implementation, you can call [`GuardDecisions::record`]
+/// from its `check` method to get the same behaviour:
+///
+/// ```rust
+/// use actix_web::guard::{Guard, GuardContext};
+/// use tracing_actix_web::GuardDecisions;
+///
+/// struct BetaTester;
+///
+/// impl Guard for BetaTester {
+///     fn check(&self, ctx: &GuardContext<'_>) -> bool {
+///         let matched = ctx.head().headers().contains_key("X-Beta-Tester");
+///         GuardDecisions::record(ctx, "beta-tester", matched);
+///         matched
+///     }
+/// }
+/// ```
+///
+/// [`DefaultRootSpanBuilder`]: crate::DefaultRootSpanBuilder
+#[derive(Clone, Debug, Default)]
+pub struct GuardDecisions {
+    decisions: Vec<(Cow<'static, str>, bool)>,
+}
+
+impl GuardDecisions {
+    /// Add the decision taken by the guard called `name` to the decisions stored in
+    /// request-local storage.
+    pub fn record(ctx: &GuardContext<'_>, name: impl Into<Cow<'static, str>>, matched: bool) {
+        let mut extensions = ctx.req_data_mut();
+        if extensions.get::<GuardDecisions>().is_none() {
+            extensions.insert(GuardDecisions::default());
+        }
+        extensions
+            .get_mut::<GuardDecisions>()
+            .unwrap()
+            .decisions
+            .push((name.into(), matched));
+    }
+
+    /// Iterate over the recorded decisions, in evaluation order.
+    ///
+    /// Each item is a tuple containing the name of the guard and whether it accepted the request.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.decisions
+            .iter()
+            .map(|(name, matched)| (name.as_ref(), *matched))
+    }
+
+    pub(crate) fn names(&self, matched: bool) -> Option<String> {
+        let names: Vec<&str> = self
+            .iter()
+            .filter(|(_, m)| *m == matched)
+            .map(|(name, _)| name)
+            .collect();
+        if names.is_empty() {
+            None
+        } else {
+            Some(names.join(","))
+        }
+    }
+}
+
+/// A [`Guard`] wrapper that records the decision of the inner guard in [`GuardDecisions`].
+///
+/// Guards determine which resource handles an incoming request, but their decisions are not
+/// visible in your telemetry data. Wrap the guards you care about with `TracedGuard` to
+/// find out, from the root span, which guards were decisive for the routing of a request.
+///
+/// # Usage
+///
+/// ```rust
+/// use actix_web::{guard, web, App, HttpResponse};
+/// use tracing_actix_web::{TracedGuard, TracingLogger};
+///
+/// let app = App::new()
+///     .wrap(TracingLogger::default())
+///     .service(
+///         web::resource("/")
+///             .guard(TracedGuard::new("api-v2", guard::Header("x-api-version", "2")))
+///             .to(HttpResponse::Ok),
+///     );
+/// ```
+pub struct TracedGuard<G> {
+    name: Cow<'static, str>,
+    guard: G,
+}
+
+impl<G: Guard> TracedGuard<G> {
+    /// Wrap `guard`, recording its decisions under `name`.
+    pub fn new(name: impl Into<Cow<'static, str>>, guard: G) -> Self {
+        Self {
+            name: name.into(),
+            guard,
+        }
+    }
+}
+
+impl<G: Guard> Guard for TracedGuard<G> {
+    fn check(&self, ctx: &GuardContext<'_>) -> bool {
+        let matched = self.guard.check(ctx);
+        GuardDecisions::record(ctx, self.name.clone(), matched);
+        matched
+    }
+}
@@ -0,0 +1,59 @@
+use actix_web::HttpMessage;
+
+/// Whether the in-flight request was served by replaying the stored result of a prior request
+/// carrying the same idempotency key.
+///
+/// `tracing-actix-web` does not detect replays: it is the job of the middleware (or handler)
+/// implementing idempotency keys. It can store its decision in request-local storage using
+/// [`IdempotencyOutcome::record`]: [`DefaultRootSpanBuilder`] records it when the request has been
+/// processed as `http.idempotency.replayed`.
+///
+/// Enable [`record_idempotency_key`] as well to capture the key itself (`http.idempotency.key`)
+/// and find the original request for a replay.
+///
+/// # Integration
+///
+/// Record the outcome as soon as you know whether the key was seen before, from a middleware
+/// wrapped by `TracingLogger` or from the handler itself:
+///
+/// ```rust
+/// use actix_web::dev::Service;
+/// use actix_web::{web, App, HttpResponse};
+/// use std::collections::HashSet;
+/// use std::sync::{Arc, Mutex};
+/// use tracing_actix_web::{IdempotencyOutcome, TracingLogger};
+///
+/// // A toy idempotency layer: a real one would store and replay the responses.
+/// let seen_keys = Arc::new(Mutex::new(HashSet::new()));
+/// let app = App::new()
+///     .wrap_fn(move |req, srv| {
+///         if let Some(key) = req.headers().get("Idempotency-Key") {
+///             let replayed = !seen_keys.lock().unwrap().insert(key.clone());
+///             IdempotencyOutcome::record(&req, replayed);
+///         }
+///         srv.call(req)
+///     })
+///     .wrap(TracingLogger::default().record_idempotency_key(true))
+///     .route("/payments", web::post().to(HttpResponse::Created));
+/// ```
+///
+/// [`DefaultRootSpanBuilder`]: crate::DefaultRootSpanBuilder
+/// [`record_idempotency_key`]: crate::TracingLogger::record_idempotency_key
+#[derive(Clone, Copy, Debug)]
+pub struct IdempotencyOutcome {
+    replayed: bool,
+}
+
+impl IdempotencyOutcome {
+    /// Store whether `request` was served by replaying a stored result in request-local storage.
+    pub fn record<R: HttpMessage>(request: &R, replayed: bool) {
+        request
+            .extensions_mut()
+            .insert(IdempotencyOutcome { replayed });
+    }
+
+    /// Whether the request was served by replaying the stored result of a prior request.
+    pub fn replayed(&self) -> bool {
+        self.replayed
+    }
+}
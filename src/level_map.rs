@@ -0,0 +1,162 @@
+use actix_web::http::StatusCode;
+use tracing::Level;
+
+/// The level of the [completion event](crate::TracingLogger::emit_completion_event) emitted
+/// for each class of status codes.
+///
+/// By default:
+///
+/// - `1xx` and `2xx` responses are logged at the `INFO` level;
+/// - `3xx` responses are logged at the `DEBUG` level;
+/// - `4xx` responses are logged at the `WARN` level;
+/// - `5xx` responses are logged at the `ERROR` level.
+///
+/// ```rust
+/// use actix_web::http::StatusCode;
+/// use tracing::Level;
+/// use tracing_actix_web::LevelMap;
+///
+/// let levels = LevelMap::default();
+/// assert_eq!(levels.level_for(StatusCode::OK), Level::INFO);
+/// assert_eq!(levels.level_for(StatusCode::NOT_MODIFIED), Level::DEBUG);
+/// assert_eq!(levels.level_for(StatusCode::NOT_FOUND), Level::WARN);
+/// assert_eq!(levels.level_for(StatusCode::SERVICE_UNAVAILABLE), Level::ERROR);
+///
+/// // Client errors are somebody else's problem
+/// let levels = LevelMap::default().client_error(Level::INFO);
+/// assert_eq!(levels.level_for(StatusCode::NOT_FOUND), Level::INFO);
+/// assert_eq!(levels.level_for(StatusCode::INTERNAL_SERVER_ERROR), Level::ERROR);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LevelMap {
+    informational: Level,
+    success: Level,
+    redirection: Level,
+    client_error: Level,
+    server_error: Level,
+}
+
+impl Default for LevelMap {
+    fn default() -> Self {
+        Self {
+            informational: Level::INFO,
+            success: Level::INFO,
+            redirection: Level::DEBUG,
+            client_error: Level::WARN,
+            server_error: Level::ERROR,
+        }
+    }
+}
+
+impl LevelMap {
+    /// The level for `1xx` responses.
+    pub fn informational(mut self, level: Level) -> Self {
+        self.informational = level;
+        self
+    }
+
+    /// The level for `2xx` responses.
+    pub fn success(mut self, level: Level) -> Self {
+        self.success = level;
+        self
+    }
+
+    /// The level for `3xx` responses.
+    pub fn redirection(mut self, level: Level) -> Self {
+        self.redirection = level;
+        self
+    }
+
+    /// The level for `4xx` responses.
+    pub fn client_error(mut self, level: Level) -> Self {
+        self.client_error = level;
+        self
+    }
+
+    /// The level for `5xx` responses.
+    pub fn server_error(mut self, level: Level) -> Self {
+        self.server_error = level;
+        self
+    }
+
+    /// The level of the completion event for a response with status `status_code`.
+    ///
+    /// Non-standard status codes (`600` and above) are treated as server errors.
+    pub fn level_for(&self, status_code: StatusCode) -> Level {
+        if status_code.is_informational() {
+            self.informational
+        } else if status_code.is_success() {
+            self.success
+        } else if status_code.is_redirection() {
+            self.redirection
+        } else if status_code.is_client_error() {
+            self.client_error
+        } else {
+            self.server_error
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(code: u16) -> StatusCode {
+        StatusCode::from_u16(code).unwrap()
+    }
+
+    /// A map with a distinct level for each class of status codes.
+    fn distinct_levels() -> LevelMap {
+        LevelMap::default()
+            .informational(Level::TRACE)
+            .success(Level::DEBUG)
+            .redirection(Level::INFO)
+            .client_error(Level::WARN)
+            .server_error(Level::ERROR)
+    }
+
+    #[test]
+    fn class_boundaries_do_not_overlap() {
+        let levels = distinct_levels();
+        let expected = [
+            (100, Level::TRACE),
+            (199, Level::TRACE),
+            (200, Level::DEBUG),
+            (299, Level::DEBUG),
+            (300, Level::INFO),
+            (399, Level::INFO),
+            (400, Level::WARN),
+            (499, Level::WARN),
+            (500, Level::ERROR),
+            (599, Level::ERROR),
+        ];
+        for (code, level) in expected {
+            assert_eq!(levels.level_for(status(code)), level, "status {}", code);
+        }
+    }
+
+    #[test]
+    fn non_standard_status_codes_fall_back_to_the_server_error_level() {
+        let levels = distinct_levels();
+        for code in [600, 999] {
+            assert_eq!(
+                levels.level_for(status(code)),
+                Level::ERROR,
+                "status {}",
+                code
+            );
+        }
+        let levels = levels.server_error(Level::WARN);
+        assert_eq!(levels.level_for(status(600)), Level::WARN);
+    }
+
+    #[test]
+    fn setters_only_affect_their_own_class() {
+        let levels = LevelMap::default().redirection(Level::WARN);
+        assert_eq!(levels.level_for(status(302)), Level::WARN);
+        assert_eq!(levels.level_for(status(200)), Level::INFO);
+        assert_eq!(levels.level_for(status(404)), Level::WARN);
+        assert_eq!(levels.level_for(status(101)), Level::INFO);
+        assert_eq!(levels.level_for(status(503)), Level::ERROR);
+    }
+}
@@ -15,13 +15,26 @@
 //! actix-web = "=4.0.0-beta.19"
 //! ```
 //!
-//! `tracing-actix-web` exposes three feature flags:
+//! `tracing-actix-web` exposes the following feature flags:
 //!
 //! - `opentelemetry_0_13`: attach [OpenTelemetry](https://github.com/open-telemetry/opentelemetry-rust)'s context to the root span using `opentelemetry` 0.13;
 //! - `opentelemetry_0_14`: same as above but using `opentelemetry` 0.14;
 //! - `opentelemetry_0_15`: same as above but using `opentelemetry` 0.15;
 //! - `opentelemetry_0_16`: same as above but using `opentelemetry` 0.16;
-//! - `emit_event_on_error`: emit a [`tracing`] event when request processing fails with an error (enabled by default).
+//! - `emit_event_on_error`: emit a [`tracing`] event when request processing fails with an error (enabled by default);
+//! - `field-host`, `field-client-ip` and `field-user-agent`: declare, respectively, the `http.host`, `http.client_ip`
+//!   and `http.user_agent` fields on the root span (enabled by default).
+//!
+//! The `field-*` features let you compile out default fields you don't need: if the corresponding feature is
+//! disabled, [`root_span!`] doesn't declare them and their value is never computed. This is useful, for example, if a
+//! library embedding `tracing-actix-web` must not record the IP addresses of its users.
+//! Cargo features are additive: a field is compiled out only if none of the crates in your dependency graph enables
+//! the corresponding feature - remember to disable the default features of `tracing-actix-web` everywhere:
+//!
+//! ```toml
+//! [dependencies]
+//! tracing-actix-web = { version = "=0.5.0-beta.9", default-features = false, features = ["emit_event_on_error", "field-host"] }
+//! ```
 //!
 //! `tracing-actix-web` will release `0.5.0`, going out of beta, as soon as `actix-web` releases a stable `4.0.0`.
 //!
@@ -162,11 +175,9 @@
 //! use actix_web::{Error, HttpResponse};
 //! use tracing_actix_web::{RootSpan, DefaultRootSpanBuilder, RootSpanBuilder};
 //! use tracing::Span;
-//! use actix_web::get;
 //! use tracing_actix_web::RequestId;
 //! use uuid::Uuid;
 //!
-//! #[get("/")]
 //! async fn handler(root_span: RootSpan) -> HttpResponse {
 //!     let application_id: &str = todo!("Some domain logic");
 //!     // Record the property value against the root span
@@ -195,6 +206,133 @@
 //! }
 //! ```
 //!
+//! ### Pre-declared fields
+//!
+//! [`root_span!`] declares, empty, a few fields whose value can only be computed by your handlers.
+//! You can record them using the [`RootSpan`] extractor without having to write your own [`RootSpanBuilder`]:
+//!
+//! - `http.request.multipart_part_count`, the number of parts in a `multipart/form-data` upload;
+//! - `enduser.credential_id`, the identifier of the credential (e.g. the id of an API key - never the secret!)
+//!   used to authenticate the request, for auditing purposes;
+//! - `db.transaction`, `true` if the request opened a database transaction;
+//! - `handler.memoized`, `true` if the handler short-circuited, serving a result it memoized while processing an
+//!   earlier request.
+//!
+//! For example, using [`actix-multipart`](https://docs.rs/actix-multipart):
+//!
+//! ```rust,ignore
+//! use actix_multipart::Multipart;
+//! use actix_web::{Error, HttpResponse};
+//! use futures_util::TryStreamExt;
+//! use tracing_actix_web::RootSpan;
+//!
+//! async fn upload(root_span: RootSpan, mut payload: Multipart) -> Result<HttpResponse, Error> {
+//!     let mut part_count: u64 = 0;
+//!     while let Some(mut field) = payload.try_next().await? {
+//!         part_count += 1;
+//!         while let Some(_chunk) = field.try_next().await? {
+//!             // [...]
+//!         }
+//!     }
+//!     root_span.record("http.request.multipart_part_count", part_count);
+//!     Ok(HttpResponse::Ok().finish())
+//! }
+//! ```
+//!
+//! Middlewares registered before [`TracingLogger`] (i.e. running after it) can record pre-declared fields too, retrieving
+//! the [`RootSpan`] from request-local storage - e.g. an authentication middleware:
+//!
+//! ```rust
+//! use actix_web::dev::Service;
+//! use actix_web::{App, HttpMessage};
+//! use tracing_actix_web::{RootSpan, TracingLogger};
+//!
+//! /// Look up the API key presented by the client, returning its (non-secret) identifier.
+//! fn authenticate(api_key: &[u8]) -> Option<String> {
+//!     todo!()
+//! }
+//!
+//! let app = App::new()
+//!     .wrap_fn(|req, srv| {
+//!         let credential_id = req.headers().get("X-Api-Key").and_then(|key| authenticate(key.as_bytes()));
+//!         if let Some(credential_id) = credential_id {
+//!             if let Some(root_span) = req.extensions().get::<RootSpan>() {
+//!                 root_span.record("enduser.credential_id", credential_id.as_str());
+//!             }
+//!         }
+//!         srv.call(req)
+//!     })
+//!     .wrap(TracingLogger::default());
+//! ```
+//!
+//! Fields can be recorded at any point while the request is being processed, including from the constructor or the
+//! `Drop` implementation of a guard. For example, you can record `db.transaction` when your transaction guard is
+//! created, so that every code path opening a transaction is accounted for - including the ones that bail out early:
+//!
+//! ```rust
+//! use actix_web::HttpResponse;
+//! use tracing_actix_web::RootSpan;
+//!
+//! /// A database transaction, rolled back when dropped unless it has been committed.
+//! struct Transaction {
+//!     committed: bool,
+//! }
+//!
+//! impl Transaction {
+//!     fn begin(root_span: &RootSpan) -> Self {
+//!         // [...] Open the transaction
+//!         root_span.record("db.transaction", true);
+//!         Self { committed: false }
+//!     }
+//!
+//!     fn commit(mut self) {
+//!         // [...]
+//!         self.committed = true;
+//!     }
+//! }
+//!
+//! impl Drop for Transaction {
+//!     fn drop(&mut self) {
+//!         if !self.committed {
+//!             // [...] Roll back the transaction
+//!         }
+//!     }
+//! }
+//!
+//! async fn transfer(root_span: RootSpan) -> HttpResponse {
+//!     let transaction = Transaction::begin(&root_span);
+//!     // [...]
+//!     transaction.commit();
+//!     HttpResponse::Ok().finish()
+//! }
+//! ```
+//!
+//! `handler.memoized` is about memoization within your handlers: it is distinct from the fields recorded by an HTTP
+//! caching layer sitting in front of them, which can serve a response without invoking your handler at all.
+//! Record it on both paths to tell apart the requests that were served a memoized result from the ones that
+//! didn't go through a memoized handler:
+//!
+//! ```rust
+//! use actix_web::{web, HttpResponse};
+//! use std::collections::HashMap;
+//! use std::sync::Mutex;
+//! use tracing_actix_web::RootSpan;
+//!
+//! type Reports = Mutex<HashMap<u32, String>>;
+//!
+//! async fn report(root_span: RootSpan, id: web::Path<u32>, reports: web::Data<Reports>) -> HttpResponse {
+//!     let id = id.into_inner();
+//!     if let Some(report) = reports.lock().unwrap().get(&id) {
+//!         root_span.record("handler.memoized", true);
+//!         return HttpResponse::Ok().body(report.clone());
+//!     }
+//!     root_span.record("handler.memoized", false);
+//!     let report = format!("Report #{}", id); // An expensive computation
+//!     reports.lock().unwrap().insert(id, report.clone());
+//!     HttpResponse::Ok().body(report)
+//! }
+//! ```
+//!
 //! # Unique identifiers
 //!
 //! ## Request Id
@@ -204,11 +342,9 @@
 //! You can extract the request id using the [`RequestId`] extractor:
 //!
 //! ```rust
-//! use actix_web::get;
 //! use tracing_actix_web::RequestId;
 //! use uuid::Uuid;
 //!
-//! #[get("/")]
 //! async fn index(request_id: RequestId) -> String {
 //!     format!("{}", request_id)
 //! }
@@ -217,6 +353,10 @@
 //! The request id is meant to identify all operations related to a particular request **within the boundary of your API**.
 //! If you need to **trace** a request across multiple services (e.g. in a microservice architecture), you want to look at the `trace_id` field - see the next section on OpenTelemetry for more details.
 //!
+//! If your API is user-facing, you can include the request id in the body of your error responses using
+//! [`with_request_id`]: your users will be able to report it back to you, making it easy to find the
+//! corresponding logs.
+//!
 //! ## Trace Id
 //!
 //! To fulfill a request you often have to perform additional I/O operations - e.g. calls to other REST or gRPC APIs, database queries, etc.
@@ -235,18 +375,45 @@
 //!
 //! [root span]: crate::RootSpan
 //! [`actix-web`]: https://docs.rs/actix-web/4.0.0-beta.13/actix_web/index.html
+mod api_version;
+mod connection;
+mod cors;
+mod fields;
+mod guard;
+mod idempotency;
+mod level_map;
 mod middleware;
+mod operation_id;
+mod otel_status;
+mod payload;
+mod pending_fields;
 mod request_id;
 mod root_span;
 mod root_span_builder;
+mod settings;
+mod unmatched_route;
+mod validation;
+mod with_request_id;
 
+pub use api_version::ApiVersionSource;
+pub use connection::{on_connect, ConnectionMetadata, TlsConnectionInfo};
+pub use cors::CorsOutcome;
+pub use guard::{GuardDecisions, TracedGuard};
+pub use idempotency::IdempotencyOutcome;
+pub use level_map::LevelMap;
 pub use middleware::TracingLogger;
-pub use request_id::RequestId;
+pub use operation_id::OperationIds;
+pub use otel_status::OtelStatus;
+pub use pending_fields::PendingSpanFields;
+pub use request_id::{InvalidRequestIdPrefix, RequestId};
 pub use root_span::RootSpan;
 pub use root_span_builder::{
-    DebugRootSpanBuilder, DefaultRootSpanBuilder, ErrorRootSpanBuilder, InfoRootSpanBuilder,
-    RootSpanBuilder, TraceRootSpanBuilder, WarnRootSpanBuilder,
+    BodyStats, DebugRootSpanBuilder, DefaultRootSpanBuilder, ErrorRootSpanBuilder,
+    InfoRootSpanBuilder, RootSpanBuilder, TraceRootSpanBuilder, WarnRootSpanBuilder,
+    WideEventRootSpanBuilder,
 };
+pub use unmatched_route::UnmatchedRoutePolicy;
+pub use with_request_id::{with_request_id, WithRequestId};
 
 #[doc(hidden)]
 pub mod root_span_macro;
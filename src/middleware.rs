@@ -1,13 +1,34 @@
-use crate::{DefaultRootSpanBuilder, RequestId, RootSpan, RootSpanBuilder};
+use crate::fields::{record, RecordedFields};
+use crate::payload::PayloadTally;
+use crate::root_span_macro::private::duration_ms;
+use crate::settings::{
+    AccessLog, AppDataProbe, ErrorCallback, InflightGuard, RequestSettings, Settings,
+    VerboseClients,
+};
+use crate::{
+    ApiVersionSource, BodyStats, ConnectionMetadata, DefaultRootSpanBuilder,
+    InvalidRequestIdPrefix, LevelMap, RequestId, RootSpan, RootSpanBuilder, TlsConnectionInfo,
+    UnmatchedRoutePolicy,
+};
 use actix_web::body::{BodySize, MessageBody};
 use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::HeaderName;
 use actix_web::http::StatusCode;
 use actix_web::web::Bytes;
 use actix_web::{Error, HttpMessage, ResponseError};
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::convert::TryFrom;
 use std::future::{ready, Future, Ready};
+use std::io::Write;
 use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
 use std::task::{Context, Poll};
-use tracing::Span;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::level_filters::LevelFilter;
+use tracing::{Level, Span};
 
 /// `TracingLogger` is a middleware to capture structured diagnostic when processing an HTTP request.
 /// Check the crate-level documentation for an in-depth introduction.
@@ -40,12 +61,16 @@ use tracing::Span;
 /// [`Compat`]: https://docs.rs/actix-web/4.0.0-beta.13/actix_web/middleware/struct.Compat.html
 /// [`tracing`]: https://docs.rs/tracing
 pub struct TracingLogger<RootSpan: RootSpanBuilder> {
+    settings: Settings,
     root_span_builder: std::marker::PhantomData<RootSpan>,
 }
 
 impl<RootSpan: RootSpanBuilder> Clone for TracingLogger<RootSpan> {
     fn clone(&self) -> Self {
-        Self::new()
+        Self {
+            settings: self.settings.clone(),
+            root_span_builder: std::marker::PhantomData,
+        }
     }
 }
 
@@ -58,9 +83,903 @@ impl Default for TracingLogger<DefaultRootSpanBuilder> {
 impl<RootSpan: RootSpanBuilder> TracingLogger<RootSpan> {
     pub fn new() -> TracingLogger<RootSpan> {
         TracingLogger {
+            settings: Settings::default(),
             root_span_builder: Default::default(),
         }
     }
+
+    /// Choose how `http.route` is populated for requests that did not match any registered route.
+    ///
+    /// It defaults to [`UnmatchedRoutePolicy::Sentinel`].
+    /// Check out [`UnmatchedRoutePolicy`] for the available options.
+    pub fn unmatched_route_policy(mut self, policy: UnmatchedRoutePolicy) -> Self {
+        self.settings.unmatched_route = policy;
+        self
+    }
+
+    /// Record the version of your API targeted by each request (`http.api_version`), as
+    /// found in `source`.
+    ///
+    /// Nothing is recorded if the version can't be determined, unless a
+    /// [default](TracingLogger::default_api_version) has been configured.
+    ///
+    /// ```rust
+    /// use tracing_actix_web::{ApiVersionSource, TracingLogger};
+    ///
+    /// // `Accept: application/vnd.acme.v2+json` is recorded as `v2`,
+    /// // `Accept: */*` falls back to `v1`.
+    /// let logger = TracingLogger::default()
+    ///     .record_api_version(ApiVersionSource::AcceptHeader)
+    ///     .default_api_version("v1");
+    /// ```
+    pub fn record_api_version(mut self, source: ApiVersionSource) -> Self {
+        self.settings.api_version_source = Some(source);
+        self
+    }
+
+    /// The version recorded as `http.api_version` when it can't be determined from the
+    /// [configured source](TracingLogger::record_api_version).
+    ///
+    /// It has no effect if `record_api_version` has not been called.
+    pub fn default_api_version(mut self, version: impl Into<String>) -> Self {
+        self.settings.default_api_version = Some(version.into());
+        self
+    }
+
+    /// Record non-standard HTTP methods as `_OTHER` in `http.method`, to keep its cardinality bounded.
+    ///
+    /// Clients can send arbitrary method strings: when normalization is enabled, any method other
+    /// than `GET`, `HEAD`, `POST`, `PUT`, `DELETE`, `CONNECT`, `OPTIONS`, `TRACE` and `PATCH` is
+    /// recorded as `_OTHER`, as prescribed by OpenTelemetry's semantic conventions.
+    /// The original method is recorded as `http.method_raw`.
+    ///
+    /// It defaults to `false`: `http.method` is recorded as-is.
+    ///
+    /// ```rust
+    /// use tracing_actix_web::TracingLogger;
+    ///
+    /// // A `PURGE` request is recorded as `http.method = "_OTHER"` and `http.method_raw = "PURGE"`.
+    /// let logger = TracingLogger::default().normalize_http_method(true);
+    /// ```
+    pub fn normalize_http_method(mut self, enabled: bool) -> Self {
+        self.settings.normalize_http_method = enabled;
+        self
+    }
+
+    /// Enable debug mode for the requests that carry an `X-Trace-Debug` header set to `secret`.
+    ///
+    /// The root span of a flagged request captures all the optional fields supported by
+    /// `tracing-actix-web`, regardless of the toggles you configured on `TracingLogger`.
+    /// On top of that, it captures:
+    ///
+    /// - the request headers (`http.request.headers`), as a JSON object. The values of
+    ///   `Authorization`, `Proxy-Authorization`, `Cookie` and `X-Trace-Debug` are redacted;
+    /// - the query string (`http.request.query`);
+    /// - the first KiB of the request body read by your handlers (`http.request.body_prefix`),
+    ///   decoded as UTF-8 - invalid sequences are replaced with `U+FFFD`. The body is not read
+    ///   ahead of your handlers: nothing is captured if they don't read it.
+    ///
+    /// This allows you to enable verbose telemetry for a single request, on demand, without
+    /// having to redeploy or reconfigure your application.
+    ///
+    /// Treat `secret` as a credential - anybody who knows it can make your application
+    /// log the headers and the body of their requests.
+    ///
+    /// ```rust
+    /// use tracing_actix_web::TracingLogger;
+    ///
+    /// let logger = TracingLogger::default().debug_secret("my-secret");
+    /// ```
+    pub fn debug_secret(mut self, secret: impl Into<String>) -> Self {
+        self.settings.debug_secret = Some(secret.into());
+        self
+    }
+
+    /// Trace verbosely the requests of the clients in `clients`, identified by the value of the
+    /// `header` request header (e.g. `X-Client-Id`).
+    ///
+    /// `clients` is consulted when each request comes in: you can add and remove clients at
+    /// runtime (e.g. from an admin endpoint) by writing to it, without restarting your
+    /// application. Keep the write lock for as little as possible - incoming requests wait for it.
+    ///
+    /// The root span of the requests of a verbose client records `tracing_actix_web.verbose = true`.
+    /// Nothing else changes: the optional fields are captured according to your toggles and,
+    /// unlike [debug mode](TracingLogger::debug_secret), their headers, query string and body are
+    /// not captured.
+    ///
+    /// # Raising the log level
+    ///
+    /// The level of a span is chosen statically, when its callsite is declared: it can't be raised
+    /// for a single request. Use `tracing_actix_web.verbose` instead to enable verbose output for
+    /// everything that happens within the root span of a verbose client, using an
+    /// [`EnvFilter`](https://docs.rs/tracing-subscriber/latest/tracing_subscriber/filter/struct.EnvFilter.html)
+    /// span field directive - e.g. `info,[{tracing_actix_web.verbose=true}]=debug`:
+    ///
+    /// ```rust
+    /// use actix_web::http::header::HeaderName;
+    /// use std::collections::HashSet;
+    /// use std::sync::{Arc, RwLock};
+    /// use tracing_actix_web::TracingLogger;
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// use tracing_subscriber::EnvFilter;
+    ///
+    /// let filter = EnvFilter::new("info,[{tracing_actix_web.verbose=true}]=debug");
+    /// let subscriber = tracing_subscriber::registry().with(filter);
+    ///
+    /// let verbose_clients = Arc::new(RwLock::new(HashSet::new()));
+    /// verbose_clients.write().unwrap().insert("acme".to_string());
+    /// // `DEBUG` events emitted while processing the requests of `acme` get through the filter.
+    /// let logger = TracingLogger::default()
+    ///     .verbose_for(verbose_clients.clone(), HeaderName::from_static("x-client-id"));
+    /// ```
+    pub fn verbose_for(
+        mut self,
+        clients: Arc<RwLock<HashSet<String>>>,
+        header: HeaderName,
+    ) -> Self {
+        self.settings.verbose_clients = Some(VerboseClients { clients, header });
+        self
+    }
+
+    /// Prepend `prefix` to the generated request ids (e.g. `svcA-67e55044-10b1-426f-9247-bb680e5fe0c8`).
+    ///
+    /// The prefix is included in the `request_id` field of the root span and in the `Display`
+    /// representation of the [`RequestId`] you get using its extractor - but not in the [`Uuid`]
+    /// it dereferences to. Check out [`RequestId`] for the details.
+    ///
+    /// It fails if `prefix` contains characters that are not printable ASCII characters
+    /// (whitespace included): request ids are often echoed back in response headers.
+    ///
+    /// ```rust
+    /// use tracing_actix_web::TracingLogger;
+    ///
+    /// let logger = TracingLogger::default().request_id_prefix("svcA-").unwrap();
+    /// assert!(TracingLogger::default().request_id_prefix("svc A-").is_err());
+    ///
+    /// // The prefix doesn't have to be known at compile time - e.g. it can be read from your
+    /// // configuration.
+    /// let service_name = String::from("svcB");
+    /// let logger = TracingLogger::default()
+    ///     .request_id_prefix(format!("{}-", service_name))
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [`Uuid`]: uuid::Uuid
+    pub fn request_id_prefix(
+        mut self,
+        prefix: impl Into<Arc<str>>,
+    ) -> Result<Self, InvalidRequestIdPrefix> {
+        let prefix = prefix.into();
+        if !prefix.bytes().all(|b| b.is_ascii_graphic()) {
+            return Err(InvalidRequestIdPrefix::new(prefix));
+        }
+        self.settings.request_id_prefix = Some(prefix).filter(|prefix| !prefix.is_empty());
+        Ok(self)
+    }
+
+    /// Record the time elapsed between the start of the processing of a request and the moment
+    /// its response is ready (`http.server.duration_ms`), in milliseconds.
+    ///
+    /// The time spent streaming the response body to the client is not included.
+    ///
+    /// # Span duration
+    ///
+    /// Some exporters derive the duration of a request from the timestamps of its root span rather
+    /// than from `http.server.duration_ms`.
+    /// `http.server.duration_ms` is measured from the creation of the root span and the root span
+    /// is only entered while the request is being processed: the two durations match, as long as
+    /// the response body is written out as soon as it is ready.
+    /// The root span is closed when the response body has been fully written (and all the handles
+    /// to the span have been dropped), therefore its duration exceeds `http.server.duration_ms`
+    /// by the time it took to stream the response body to the client.
+    ///
+    /// ```rust
+    /// use tracing_actix_web::TracingLogger;
+    ///
+    /// let logger = TracingLogger::default().record_duration(true);
+    /// ```
+    pub fn record_duration(mut self, enabled: bool) -> Self {
+        self.settings.record_duration = enabled;
+        self
+    }
+
+    /// Record the time spent actively processing a request (`http.server.busy_ms`), in milliseconds.
+    ///
+    /// A request is being actively processed when `TracingLogger` is polling the future returned by
+    /// the services it wraps - i.e. when your handler (or one of the middlewares registered after
+    /// `TracingLogger`) is running, as opposed to waiting on I/O or other tasks.
+    /// Comparing `http.server.busy_ms` with [`http.server.duration_ms`] helps you tell apart
+    /// CPU-bound and I/O-bound handlers.
+    ///
+    /// Work that does not happen inside the request future is not accounted for - e.g. tasks spawned
+    /// by your handler or blocking work offloaded to a thread pool using `web::block`.
+    /// If you need busy time for all your spans, check out the `FmtSpan::CLOSE` option of
+    /// [`tracing-subscriber`](https://docs.rs/tracing-subscriber)'s formatting layer.
+    ///
+    /// The overhead is two calls to [`Instant::now`] every time the request future is polled.
+    ///
+    /// [`http.server.duration_ms`]: TracingLogger::record_duration
+    pub fn record_busy_time(mut self, enabled: bool) -> Self {
+        self.settings.record_busy_time = enabled;
+        self
+    }
+
+    /// Record the deadline advertised by the client (`http.deadline_ms`) and how much of it was
+    /// left when the response was ready (`http.deadline_remaining_ms`), in milliseconds.
+    ///
+    /// The deadline is read from the `grpc-timeout` header (e.g. `250m`) or, if it is missing, from
+    /// the `X-Request-Timeout` header (a number of milliseconds). Malformed values are ignored.
+    /// The deadline is measured from the moment `TracingLogger` started processing the request:
+    /// time spent in transit or in earlier middlewares is not accounted for.
+    ///
+    /// `http.deadline_remaining_ms` is negative if the deadline was exceeded - alert on it!
+    pub fn record_deadline(mut self, enabled: bool) -> Self {
+        self.settings.record_deadline = enabled;
+        self
+    }
+
+    /// Record the number of requests being processed when a request comes in, including the
+    /// request itself (`server.inflight_requests`).
+    ///
+    /// Correlate it with the latency of your requests to find out how it is affected by load.
+    /// A request stops being counted when its response is ready - or when it is cancelled.
+    ///
+    /// The count is shared by all the clones of this `TracingLogger`: if you want a count across
+    /// all the workers of your `HttpServer`, build `TracingLogger` outside of your application
+    /// factory and clone it into it.
+    ///
+    /// ```rust
+    /// use actix_web::{App, HttpServer};
+    /// use tracing_actix_web::TracingLogger;
+    ///
+    /// # fn run() -> std::io::Result<()> {
+    /// let logger = TracingLogger::default().record_inflight_requests(true);
+    /// let server = HttpServer::new(move || App::new().wrap(logger.clone()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn record_inflight_requests(mut self, enabled: bool) -> Self {
+        self.settings.inflight_requests = if enabled {
+            Some(Arc::new(AtomicUsize::new(0)))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Record the [JA3](https://github.com/salesforce/ja3) fingerprint of the client's TLS
+    /// handshake (`tls.ja3`), read from the `header` request header.
+    ///
+    /// `tracing-actix-web` does not compute TLS fingerprints: it expects a fronting layer that
+    /// terminates TLS (e.g. a load balancer or a CDN) to inject them as a request header
+    /// (e.g. `X-JA3-Fingerprint`). Values longer than 256 bytes are truncated; nothing is recorded
+    /// if the header is missing.
+    ///
+    /// Make sure that the header can't be set by clients - i.e. your proxy overwrites it -
+    /// before relying on it for bot or abuse detection.
+    ///
+    /// ```rust
+    /// use actix_web::http::header::HeaderName;
+    /// use tracing_actix_web::TracingLogger;
+    ///
+    /// let logger = TracingLogger::default()
+    ///     .record_ja3(HeaderName::from_static("x-ja3-fingerprint"))
+    ///     .record_ja4(HeaderName::from_static("x-ja4-fingerprint"));
+    /// ```
+    pub fn record_ja3(mut self, header: HeaderName) -> Self {
+        self.settings.ja3_header = Some(header);
+        self
+    }
+
+    /// Record the [JA4](https://github.com/FoxIO-LLC/ja4) fingerprint of the client's TLS
+    /// handshake (`tls.ja4`), read from the `header` request header.
+    ///
+    /// As for [`record_ja3`](TracingLogger::record_ja3), the fingerprint must be injected by
+    /// a fronting layer that terminates TLS.
+    pub fn record_ja4(mut self, header: HeaderName) -> Self {
+        self.settings.ja4_header = Some(header);
+        self
+    }
+
+    /// Classify the channel each request comes from - e.g. `web`, `mobile` or `api` - using
+    /// `classifier` and record it as `client.channel`.
+    ///
+    /// `classifier` is invoked once per request, before the root span is created: derive the
+    /// channel from whatever your clients send you (the `User-Agent` header, a custom header, the
+    /// path, etc.). It returns a `&'static str` on purpose: stick to a small, fixed set of values
+    /// to keep the cardinality of the field low - it's meant for grouping and filtering.
+    /// Nothing is recorded if it returns `None`.
+    ///
+    /// `classifier` is invoked in the request path: it must be fast and it must not block.
+    ///
+    /// ```rust
+    /// use actix_web::dev::ServiceRequest;
+    /// use tracing_actix_web::TracingLogger;
+    ///
+    /// fn channel(request: &ServiceRequest) -> Option<&'static str> {
+    ///     let header = |name| {
+    ///         request
+    ///             .headers()
+    ///             .get(name)
+    ///             .and_then(|value| value.to_str().ok())
+    ///     };
+    ///     let user_agent = header("User-Agent").unwrap_or("").to_ascii_lowercase();
+    ///     if user_agent.contains("bot") || user_agent.contains("crawler") {
+    ///         return Some("bot");
+    ///     }
+    ///     // Our mobile apps identify themselves using a custom header.
+    ///     match header("X-Client-Platform") {
+    ///         Some("ios") | Some("android") => Some("mobile"),
+    ///         _ if request.headers().contains_key("X-Api-Key") => Some("api"),
+    ///         _ if user_agent.starts_with("mozilla/") => Some("web"),
+    ///         _ => None,
+    ///     }
+    /// }
+    ///
+    /// let logger = TracingLogger::default().classify_channel(channel);
+    /// ```
+    pub fn classify_channel<F>(mut self, classifier: F) -> Self
+    where
+        F: Fn(&ServiceRequest) -> Option<&'static str> + Send + Sync + 'static,
+    {
+        self.settings.channel_classifier = Some(Arc::new(classifier));
+        self
+    }
+
+    /// Record the `Idempotency-Key` header of the request (`http.idempotency.key`).
+    ///
+    /// Pair it with [`IdempotencyOutcome`] to tell apart the requests served by replaying a stored
+    /// result from the original ones - and to find the original request for a replay.
+    /// Values longer than 256 bytes are truncated; nothing is recorded if the header is missing.
+    ///
+    /// ```rust
+    /// use tracing_actix_web::TracingLogger;
+    ///
+    /// let logger = TracingLogger::default().record_idempotency_key(true);
+    /// ```
+    ///
+    /// [`IdempotencyOutcome`]: crate::IdempotencyOutcome
+    pub fn record_idempotency_key(mut self, enabled: bool) -> Self {
+        self.settings.record_idempotency_key = enabled;
+        self
+    }
+
+    /// Add `host` to the list of allowed hosts and record whether the effective host of each
+    /// request is part of it (`http.host.allowed`).
+    ///
+    /// Use it to surface `Host` header injection attempts in your traces: requests are never
+    /// rejected, that's up to your application. The effective host is the one reported by
+    /// [`ConnectionInfo::host`] - i.e. it honours the `Forwarded` and `X-Forwarded-Host` headers.
+    /// The comparison ignores the port and is case-insensitive.
+    ///
+    /// A host starting with `*.` matches any subdomain: `*.example.com` matches
+    /// `api.example.com` and `eu.api.example.com`, but not `example.com` - allow it separately.
+    ///
+    /// Nothing is recorded until at least one host has been allowed.
+    ///
+    /// ```rust
+    /// use tracing_actix_web::TracingLogger;
+    ///
+    /// // `example.com` and `API.example.com:8443` are allowed,
+    /// // `example.com.evil.io` and `evilexample.com` are not.
+    /// let logger = TracingLogger::default()
+    ///     .allowed_host("example.com")
+    ///     .allowed_host("*.example.com");
+    /// ```
+    ///
+    /// [`ConnectionInfo::host`]: actix_web::dev::ConnectionInfo::host
+    pub fn allowed_host(mut self, host: impl Into<String>) -> Self {
+        self.settings
+            .allowed_hosts
+            .push(host.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Record the `Transfer-Encoding` header of the request (`http.request.transfer_encoding`,
+    /// e.g. `chunked` or `gzip, chunked`).
+    ///
+    /// Use it to tell apart chunked uploads from the ones whose size is declared upfront using
+    /// `Content-Length`. Nothing is recorded if the header is missing.
+    ///
+    /// ```rust
+    /// use tracing_actix_web::TracingLogger;
+    ///
+    /// let logger = TracingLogger::default().record_transfer_encoding(true);
+    /// ```
+    pub fn record_transfer_encoding(mut self, enabled: bool) -> Self {
+        self.settings.record_transfer_encoding = enabled;
+        self
+    }
+
+    /// Record the byte range requested by the client using the `Range` header
+    /// (`http.request.range`, e.g. `bytes=0-1023`).
+    ///
+    /// Values longer than 256 bytes (e.g. multi-range requests) are truncated.
+    /// Nothing is recorded if the header is missing.
+    pub fn record_range(mut self, enabled: bool) -> Self {
+        self.settings.record_range = enabled;
+        self
+    }
+
+    /// Record the value of the `Max-Forwards` header (`http.request.max_forwards`), as an integer.
+    ///
+    /// It is useful to debug the loop-prevention behaviour of proxies handling `TRACE` or
+    /// `OPTIONS` requests.
+    /// Nothing is recorded if the header is missing or if its value is not a non-negative integer.
+    ///
+    /// ```rust
+    /// use tracing_actix_web::TracingLogger;
+    ///
+    /// let logger = TracingLogger::default().record_max_forwards(true);
+    /// ```
+    pub fn record_max_forwards(mut self, enabled: bool) -> Self {
+        self.settings.record_max_forwards = enabled;
+        self
+    }
+
+    /// Record whether the request is a conditional request - i.e. it carries an `If-None-Match`
+    /// or an `If-Modified-Since` header (`http.request.conditional`).
+    ///
+    /// Correlate it with `http.status_code` to understand how often conditional requests are
+    /// served a `304 Not Modified`.
+    /// The value of the `If-None-Match` header is recorded as well (`http.request.if_none_match`),
+    /// unless it is longer than 128 bytes.
+    pub fn record_conditional(mut self, enabled: bool) -> Self {
+        self.settings.record_conditional = enabled;
+        self
+    }
+
+    /// Record a coarse classification of the outcome of each request (`http.outcome`):
+    ///
+    /// - `success`, for `1xx`, `2xx` and `3xx` responses;
+    /// - `client_error`, for `4xx` responses;
+    /// - `server_error`, for `5xx` responses (and non-standard status codes, `600` and above);
+    /// - `cancelled`, if the request was dropped before a response was produced - e.g. because the
+    ///   client disconnected or a timeout middleware registered before `TracingLogger` gave up on it.
+    ///
+    /// Requests that produced a response but failed while streaming the response body are
+    /// classified according to their status code.
+    pub fn record_outcome(mut self, enabled: bool) -> Self {
+        self.settings.record_outcome = enabled;
+        self
+    }
+
+    /// Record the value of the `ETag` header of the response (`http.response.etag`).
+    ///
+    /// Use it, together with [`record_conditional`](TracingLogger::record_conditional), to verify
+    /// that your handlers emit stable entity tags and to find out why conditional requests are not
+    /// served a `304 Not Modified`.
+    /// Nothing is recorded if the header is missing or if its value is longer than 128 bytes.
+    pub fn record_etag(mut self, enabled: bool) -> Self {
+        self.settings.record_etag = enabled;
+        self
+    }
+
+    /// Record the value of the `Server-Timing` header set by your handlers
+    /// (`http.response.server_timing`).
+    ///
+    /// Browsers expose `Server-Timing` metrics to real user monitoring scripts: recording them on
+    /// the root span lets you correlate client-side performance data with server-side traces.
+    /// Multiple `Server-Timing` headers are joined with `, `, and the value is truncated to
+    /// 512 bytes. Nothing is recorded if the header is missing.
+    ///
+    /// ```rust
+    /// use tracing_actix_web::TracingLogger;
+    ///
+    /// // `Server-Timing: db;dur=42` and `Server-Timing: cache;dur=1` are recorded
+    /// // as `db;dur=42, cache;dur=1`.
+    /// let logger = TracingLogger::default().record_server_timing(true);
+    /// ```
+    pub fn record_server_timing(mut self, enabled: bool) -> Self {
+        self.settings.record_server_timing = enabled;
+        self
+    }
+
+    /// Record whether the request carries an `Authorization` header (`http.request.authenticated`).
+    ///
+    /// Neither the authentication scheme nor the credentials are recorded: it is safe to enable
+    /// even under the strictest compliance regimes.
+    /// It only tells you whether the client attempted to authenticate - not whether the
+    /// credentials were valid.
+    pub fn record_authenticated(mut self, enabled: bool) -> Self {
+        self.settings.record_authenticated = enabled;
+        self
+    }
+
+    /// Compare the size of the request body declared in its `Content-Length` header with the
+    /// number of bytes that were actually read from it.
+    ///
+    /// When the response is ready, the root span records:
+    ///
+    /// - the number of bytes read from the request body (`http.request.body_bytes_read`);
+    /// - whether it differs from `Content-Length` (`http.request.content_length_mismatch`).
+    ///
+    /// The request body is only read if your handler (or an extractor) consumes it. If it was not
+    /// read in full, `http.request.content_length_mismatch` is only recorded if more bytes than
+    /// declared were read. It is not recorded for requests without a (valid) `Content-Length`.
+    ///
+    /// The request payload is wrapped to tally the bytes read from it: this costs an allocation
+    /// per request and a dynamic dispatch for every chunk of the request body.
+    pub fn record_content_length_mismatch(mut self, enabled: bool) -> Self {
+        self.settings.record_content_length_mismatch = enabled;
+        self
+    }
+
+    /// Record the size of the response body that was written out (`http.response.bytes_written`),
+    /// in bytes.
+    ///
+    /// Unlike `Content-Length`, it accounts for streaming bodies and for bodies that were not
+    /// sent in full (e.g. because the client disconnected).
+    /// Bytes are tallied as the response body is streamed: the value is recorded when the body
+    /// has been fully written out (or dropped).
+    /// It reflects compression if the compression middleware is registered after `TracingLogger`
+    /// (i.e. it runs closer to your handlers). Response headers and the framing overhead of the
+    /// HTTP protocol (e.g. chunked encoding) are not included.
+    ///
+    /// The overhead is an addition for every chunk of the response body.
+    pub fn record_bytes_written(mut self, enabled: bool) -> Self {
+        self.settings.record_bytes_written = enabled;
+        self
+    }
+
+    /// Record the time elapsed between the connection being accepted and the response body
+    /// being complete (`net.full_duration_ms`), in milliseconds.
+    ///
+    /// Unlike `http.server.duration_ms`, it includes the time spent setting up the connection
+    /// (e.g. the TLS handshake), receiving the request and writing out the response: use it to
+    /// measure latency end-to-end, as experienced by clients opening a new connection.
+    /// It is only recorded for the first request served over a connection - the setup cost is
+    /// not borne by the requests reusing it.
+    ///
+    /// It requires [`on_connect`] to be registered - nothing is recorded otherwise. It is measured
+    /// from the instant `on_connect` was invoked, moved back by the duration of the TLS handshake
+    /// if it is [known](crate::TlsConnectionInfo::with_handshake_duration): set the instant each
+    /// connection was accepted to include the time spent before that too - see
+    /// [`ConnectionMetadata`](crate::ConnectionMetadata#accept-time).
+    ///
+    /// [`on_connect`]: crate::on_connect
+    pub fn record_full_duration(mut self, enabled: bool) -> Self {
+        self.settings.record_full_duration = enabled;
+        self
+    }
+
+    /// Record the rate at which the response body has been written out
+    /// (`http.response.throughput_bps`), in bytes per second.
+    ///
+    /// It is computed, once the response body is complete, as the number of bytes written divided
+    /// by the time elapsed since the response head was ready. Use it to spot slow clients and
+    /// backpressure on large or streamed responses.
+    ///
+    /// Nothing is recorded for empty bodies, nor if no time elapsed - e.g. for small buffered bodies
+    /// on a coarse-grained clock. The value includes the time the body spent waiting on your
+    /// stream (e.g. a slow producer), not just on the client.
+    pub fn record_throughput(mut self, enabled: bool) -> Self {
+        self.settings.record_throughput = enabled;
+        self
+    }
+
+    /// Record `cost` (`http.request.cost`) for the requests matching the route `pattern`.
+    ///
+    /// `pattern` must be the templated route, as it appears in `http.route` (e.g. `/users/{id}`).
+    /// Use it to surface, in your telemetry, the weight of each request for quota accounting.
+    ///
+    /// Requests whose route has no registered cost - including requests that did not match any
+    /// route - record the [`default_route_cost`], if set. Nothing is recorded otherwise.
+    ///
+    /// ```rust
+    /// use tracing_actix_web::TracingLogger;
+    ///
+    /// let logger = TracingLogger::default()
+    ///     .route_cost("/reports/{id}", 10)
+    ///     .route_cost("/search", 5)
+    ///     .default_route_cost(1);
+    /// ```
+    ///
+    /// [`default_route_cost`]: TracingLogger::default_route_cost
+    pub fn route_cost(mut self, pattern: impl Into<String>, cost: u64) -> Self {
+        self.settings.route_costs.insert(pattern.into(), cost);
+        self
+    }
+
+    /// The cost recorded (`http.request.cost`) for requests whose route has no cost registered
+    /// using [`route_cost`](TracingLogger::route_cost).
+    pub fn default_route_cost(mut self, cost: u64) -> Self {
+        self.settings.default_route_cost = Some(cost);
+        self
+    }
+
+    /// Mark the requests matching the route `pattern` as sensitive (e.g. login or payment
+    /// endpoints): none of the optional fields are captured for them.
+    ///
+    /// `pattern` must be the templated route, as it appears in `http.route` (e.g. `/users/{id}`).
+    ///
+    /// It is a safety net that takes precedence over all other capture settings:
+    ///
+    /// - fields enabled using the `record_*` methods of `TracingLogger` (e.g.
+    ///   [`record_range`](TracingLogger::record_range) or
+    ///   [`record_inflight_requests`](TracingLogger::record_inflight_requests)) are not recorded;
+    /// - [debug mode](TracingLogger::debug_secret) is ignored: request headers, query string and
+    ///   all other debug fields are not recorded, even if the request carries the debug secret.
+    ///
+    /// The fields that are always recorded (e.g. `http.method`, `http.route` or
+    /// `http.status_code`) are still recorded - `http.target` without its query string - as well as the ones you record explicitly - using
+    /// the [`RootSpan`](crate::RootSpan) extractor or [`PendingSpanFields`](crate::PendingSpanFields).
+    ///
+    /// ```rust
+    /// use tracing_actix_web::TracingLogger;
+    ///
+    /// // The request headers of `POST /login` are not captured, even if it carries the debug secret.
+    /// let logger = TracingLogger::default()
+    ///     .debug_secret("my-secret")
+    ///     .sensitive_route("/login");
+    /// ```
+    pub fn sensitive_route(mut self, pattern: impl Into<String>) -> Self {
+        self.settings.sensitive_routes.insert(pattern.into());
+        self
+    }
+
+    /// Invoke `callback` every time the processing of a request fails with an error whose status
+    /// code is greater than or equal to `threshold`.
+    ///
+    /// `callback` receives the status code of the response and the `Display` representation of
+    /// the error - the same values recorded in `http.status_code` and `exception.message`.
+    /// Use it to feed your alerting (e.g. increment a counter or push to a channel) without
+    /// having to parse your logs.
+    ///
+    /// `callback` is invoked in the request path, inside the root span: it must be fast and it
+    /// must not block.
+    ///
+    /// ```rust
+    /// use actix_web::http::StatusCode;
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    /// use std::sync::Arc;
+    /// use tracing_actix_web::TracingLogger;
+    ///
+    /// let server_errors = Arc::new(AtomicU64::new(0));
+    /// let counter = server_errors.clone();
+    /// let logger = TracingLogger::default().on_error(
+    ///     StatusCode::INTERNAL_SERVER_ERROR,
+    ///     move |_status_code, _message| {
+    ///         counter.fetch_add(1, Ordering::Relaxed);
+    ///     },
+    /// );
+    /// ```
+    pub fn on_error<F>(mut self, threshold: StatusCode, callback: F) -> Self
+    where
+        F: Fn(StatusCode, &str) + Send + Sync + 'static,
+    {
+        self.settings.on_error = Some(ErrorCallback {
+            threshold,
+            callback: Arc::new(callback),
+        });
+        self
+    }
+
+    /// Write a JSON access-log line to `sink` for every request, once it has been processed.
+    ///
+    /// Each line is a self-contained JSON object carrying the values recorded on the root span -
+    /// the same fields as the event emitted by [`WideEventRootSpanBuilder`] - plus the time the line
+    /// was written (`timestamp_ms`, in milliseconds since the UNIX epoch). Lines are written
+    /// independently of your `tracing::Subscriber`: use it to ship JSON access logs to a dedicated
+    /// sink without changing the format of the rest of your logs.
+    ///
+    /// Lines are written once the response body has been sent to the client (or once the request
+    /// has failed or has been cancelled): they include the fields recorded on body completion,
+    /// like `http.response.bytes_written` and `net.full_duration_ms`, if enabled.
+    /// Optional fields are only included if they are recorded on the root span - e.g. enable
+    /// [`record_duration`](TracingLogger::record_duration) to include `http.server.duration_ms`.
+    /// Requests whose root span is disabled (e.g. skipped using
+    /// [`trace_if_body_over`](TracingLogger::trace_if_body_over) or filtered out by your
+    /// subscriber) are not logged.
+    ///
+    /// `sink` is shared by all the clones of this `TracingLogger` - and therefore by all the
+    /// workers of your `HttpServer` if you build `TracingLogger` outside of your application
+    /// factory. Writes are serialized by a lock; errors are ignored.
+    ///
+    /// # Performance
+    ///
+    /// Each line is serialized and written in the request path, while holding the lock: a slow
+    /// sink (e.g. an unbuffered [`File`](std::fs::File)) adds its latency to every request and it
+    /// makes workers contend on the lock. Wrap files in a [`BufWriter`](std::io::BufWriter) -
+    /// keeping in mind that buffered lines are lost if the process is killed - or hand the lines
+    /// over to a background thread using a channel-backed writer.
+    /// On top of that, a copy of each value recorded on the root span is kept until the line has
+    /// been written.
+    ///
+    /// ```rust
+    /// use actix_web::{test, web, App, HttpResponse};
+    /// use std::io::Write;
+    /// use std::sync::{Arc, Mutex};
+    /// use tracing_actix_web::TracingLogger;
+    ///
+    /// /// An in-memory sink, shared with the test.
+    /// #[derive(Clone, Default)]
+    /// struct Lines(Arc<Mutex<Vec<u8>>>);
+    ///
+    /// impl Write for Lines {
+    ///     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    ///         self.0.lock().unwrap().write(buf)
+    ///     }
+    ///     fn flush(&mut self) -> std::io::Result<()> {
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// // Lines are built from the values recorded on the root span: it must be enabled.
+    /// let _guard = tracing::subscriber::set_default(tracing_subscriber::registry());
+    /// let lines = Lines::default();
+    /// actix_web::rt::System::new().block_on(async {
+    ///     let app = App::new()
+    ///         .wrap(TracingLogger::default().json_access_log(lines.clone()))
+    ///         .route("/users/{id}", web::get().to(HttpResponse::Ok));
+    ///     let app = test::init_service(app).await;
+    ///     let request = test::TestRequest::get().uri("/users/42").to_request();
+    ///     test::call_service(&app, request).await;
+    /// });
+    ///
+    /// let lines = String::from_utf8(lines.0.lock().unwrap().clone()).unwrap();
+    /// let line: serde_json::Value = serde_json::from_str(lines.trim_end()).unwrap();
+    /// assert_eq!(line["http.route"], "/users/{id}");
+    /// assert_eq!(line["http.status_code"], 200);
+    /// ```
+    ///
+    /// [`WideEventRootSpanBuilder`]: crate::WideEventRootSpanBuilder
+    pub fn json_access_log<W: Write + Send + 'static>(mut self, sink: W) -> Self {
+        self.settings.access_log = Some(Arc::new(Mutex::new(Box::new(sink))));
+        self
+    }
+
+    /// Emit an event (`HTTP request completed`) when the response to a request is ready.
+    ///
+    /// The event carries the status code of the response (`http.status_code`) and it is emitted
+    /// within the root span, at the level chosen by the configured [`LevelMap`] - check out
+    /// [`completion_event_levels`](TracingLogger::completion_event_levels).
+    /// It is useful if you consume your logs as a flat list of events, rather than as spans.
+    ///
+    /// ```rust
+    /// use tracing_actix_web::{LevelMap, TracingLogger};
+    ///
+    /// let logger = TracingLogger::default()
+    ///     .emit_completion_event(true)
+    ///     .completion_event_levels(LevelMap::default().redirection(tracing::Level::INFO));
+    /// ```
+    pub fn emit_completion_event(mut self, enabled: bool) -> Self {
+        self.settings.emit_completion_event = enabled;
+        self
+    }
+
+    /// Choose the level of the [completion event](TracingLogger::emit_completion_event) for each
+    /// class of status codes.
+    ///
+    /// It defaults to [`LevelMap::default`].
+    pub fn completion_event_levels(mut self, levels: LevelMap) -> Self {
+        self.settings.completion_event_levels = levels;
+        self
+    }
+
+    /// Check, for requests flagged for [debugging](TracingLogger::debug_secret), whether a value of
+    /// type `T` is available as application data.
+    ///
+    /// When a handler fails because it expected some application data that was never registered
+    /// (e.g. `Data<PgPool>`), it can be difficult to figure out what went wrong from your telemetry.
+    /// The root span of a flagged request captures the types of the probed application data that
+    /// were available to its handler (`http.app_data.types`) and the ones that were not
+    /// (`http.app_data.missing`).
+    ///
+    /// `actix-web` does not allow us to list all the application data that is available:
+    /// you need to probe, one by one, the types you are interested in.
+    ///
+    /// ```rust
+    /// use actix_web::web::Data;
+    /// use tracing_actix_web::TracingLogger;
+    ///
+    /// struct DatabasePool;
+    ///
+    /// let logger = TracingLogger::default()
+    ///     .debug_secret("my-secret")
+    ///     .probe_app_data::<Data<DatabasePool>>();
+    /// ```
+    pub fn probe_app_data<T: 'static>(mut self) -> Self {
+        self.settings.app_data_probes.push(AppDataProbe::new::<T>());
+        self
+    }
+
+    /// Record the time spent by `TracingLogger` to create the root span
+    /// (`tracing_actix_web.overhead_us`), in microseconds.
+    ///
+    /// # Diagnostics
+    ///
+    /// It is a diagnostic field, meant to quantify the overhead of the middleware itself - e.g. to
+    /// measure the cost of the optional fields you enabled. It should stay disabled in production.
+    ///
+    /// It covers the generation of the request id, the evaluation of the settings that apply to
+    /// the request and the creation of the root span by [`RootSpanBuilder::on_request_start`] -
+    /// including the computation of all its fields.
+    /// If the creation of the root span has been [deferred](TracingLogger::lazy_root_span), it only
+    /// covers the call to `on_request_start`.
+    ///
+    /// ```rust
+    /// use tracing_actix_web::TracingLogger;
+    ///
+    /// let logger = TracingLogger::default().record_overhead(true);
+    /// ```
+    pub fn record_overhead(mut self, enabled: bool) -> Self {
+        self.settings.record_overhead = enabled;
+        self
+    }
+
+    /// Defer the creation of the root span until the response is ready.
+    ///
+    /// # Experimental
+    ///
+    /// This mode is experimental: it might change, or be removed, in any release.
+    ///
+    /// The root span is not created when a request comes in: it is created, and immediately
+    /// processed by [`RootSpanBuilder::on_request_end`], once the response is ready.
+    /// The root span is not entered while the request is being processed, therefore there is
+    /// nothing to do every time the request future is polled.
+    ///
+    /// The savings are modest and the root span loses a lot of its value:
+    ///
+    /// - `Span::current()` is not the root span while the request is being processed: the spans
+    ///   and events emitted by your handlers are not children of the root span;
+    /// - the [`RootSpan`] extractor returns a [disabled](tracing::Span::none) span: only the values
+    ///   recorded using [`RootSpan::record`] (including [`RootSpan::record_flag`]) are recorded on
+    ///   the root span once it has been created, values recorded on the span directly are lost;
+    /// - the timestamps of the root span don't reflect the processing of the request - use
+    ///   [`record_duration`](TracingLogger::record_duration) to capture it;
+    /// - no root span is created for the requests that fail with an error which has not been
+    ///   converted into a response, since it does not carry the request.
+    ///
+    /// Fields recorded when the response body has been written out are not affected.
+    ///
+    /// If [`RootSpanBuilder::LEVEL`] is set and spans at that level are disabled (see
+    /// [`LevelFilter::current`](tracing::level_filters::LevelFilter::current)), requests are not
+    /// traced at all.
+    ///
+    /// Only enable it if you rely exclusively on the fields recorded on the root span, on a path
+    /// where throughput is critical.
+    pub fn lazy_root_span(mut self, enabled: bool) -> Self {
+        self.settings.lazy_root_span = enabled;
+        self
+    }
+
+    /// Only create a root span for requests whose body is larger than `bytes`, according to their
+    /// `Content-Length` header.
+    ///
+    /// Requests without a (valid) `Content-Length` header - e.g. chunked uploads or requests without a
+    /// body - are traced, unless you opt out using [`trace_if_body_size_unknown`].
+    ///
+    /// Skipped requests get a [disabled](tracing::Span::none) root span: the [`RootSpan`] and
+    /// [`RequestId`] extractors keep working, but nothing is recorded.
+    /// The `emit_event_on_error` feature still emits an event if the processing of a skipped
+    /// request fails.
+    ///
+    /// ```rust
+    /// use tracing_actix_web::TracingLogger;
+    ///
+    /// // Only trace uploads larger than 1 MB
+    /// let logger = TracingLogger::default()
+    ///     .trace_if_body_over(1024 * 1024)
+    ///     .trace_if_body_size_unknown(false);
+    /// ```
+    ///
+    /// [`trace_if_body_size_unknown`]: TracingLogger::trace_if_body_size_unknown
+    pub fn trace_if_body_over(mut self, bytes: u64) -> Self {
+        self.settings.body_size_threshold = Some(bytes);
+        self
+    }
+
+    /// Choose whether requests with an unknown body size are traced when a body size threshold
+    /// has been set using [`trace_if_body_over`].
+    ///
+    /// It defaults to `true`. It has no effect if no threshold has been set.
+    ///
+    /// [`trace_if_body_over`]: TracingLogger::trace_if_body_over
+    pub fn trace_if_body_size_unknown(mut self, trace: bool) -> Self {
+        self.settings.skip_unknown_body_size = !trace;
+        self
+    }
 }
 
 impl<S, B, RootSpan> Transform<S, ServiceRequest> for TracingLogger<RootSpan>
@@ -79,7 +998,8 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ready(Ok(TracingLoggerMiddleware {
             service,
-            root_span_builder: std::marker::PhantomData::default(),
+            settings: Rc::new(self.settings.clone()),
+            root_span_builder: Default::default(),
         }))
     }
 }
@@ -87,6 +1007,7 @@ where
 #[doc(hidden)]
 pub struct TracingLoggerMiddleware<S, RootSpanBuilder> {
     service: S,
+    settings: Rc<Settings>,
     root_span_builder: std::marker::PhantomData<RootSpanBuilder>,
 }
 
@@ -104,38 +1025,228 @@ where
 
     actix_web::dev::forward_ready!(service);
 
-    fn call(&self, req: ServiceRequest) -> Self::Future {
-        req.extensions_mut().insert(RequestId::generate());
-        let root_span = RootSpanType::on_request_start(&req);
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let received_at = Instant::now();
+        let request_id = RequestId::generate().with_prefix(self.settings.request_id_prefix.clone());
+        req.extensions_mut().insert(request_id.clone());
+        let sensitive = self.settings.is_sensitive(&req);
+        let verbose = !sensitive && self.settings.is_verbose_request(&req);
+        let debug = !sensitive && self.settings.is_debug_request(&req);
+        let (inflight_guard, inflight_requests) = match &self.settings.inflight_requests {
+            Some(counter) => {
+                let (guard, count) = InflightGuard::new(counter);
+                (Some(guard), Some(count).filter(|_| !sensitive))
+            }
+            None => (None, None),
+        };
+        let mut request_settings = RequestSettings {
+            settings: self.settings.clone(),
+            debug,
+            verbose,
+            sensitive,
+            started_at: Cell::new(Instant::now()),
+            deadline: None,
+            inflight_requests,
+            channel: None,
+            connection_accepted_at: None,
+            connection_reused: None,
+            time_to_first_request: None,
+            recorded_fields: None,
+        };
+        if RootSpanType::RECORD_FIELDS || self.settings.access_log.is_some() {
+            request_settings.recorded_fields = Some(RecordedFields::default());
+        }
+        if request_settings.capture(self.settings.record_deadline) {
+            request_settings.deadline = self.settings.deadline(&req);
+        }
+        // Registering a classifier is the toggle - it doesn't override sensitive routes.
+        if request_settings.capture(true) {
+            request_settings.channel = self.settings.channel(&req);
+        }
+        // Snapshotted before the request is counted against its connection, since the root span
+        // might be created once it has been processed.
+        if let Some(connection) = req.conn_data::<ConnectionMetadata>() {
+            let first_request = connection.served_requests() == 0;
+            request_settings.connection_reused = Some(!first_request);
+            // Later requests on the connection didn't wait for it to be set up.
+            if first_request {
+                request_settings.time_to_first_request =
+                    Some(connection.established_at().elapsed());
+                if request_settings.capture(self.settings.record_full_duration) {
+                    let tls = req.conn_data::<TlsConnectionInfo>();
+                    request_settings.connection_accepted_at = Some(connection.accepted_since(tls));
+                }
+            }
+        }
+        let request_settings = Rc::new(request_settings);
+        req.extensions_mut().insert(request_settings.clone());
+        let mut deferred = None;
+        let root_span = if !self.settings.should_trace(&req) {
+            Span::none()
+        } else if self.settings.lazy_root_span {
+            // There is no point in deferring the creation of a span that would be disabled.
+            if !is_disabled(RootSpanType::LEVEL) {
+                deferred = Some(RecordedFields::default());
+            }
+            Span::none()
+        } else {
+            RecordedFields::in_scope(request_settings.recorded_fields.as_ref(), || {
+                let root_span = RootSpanType::on_request_start(&req);
+                if request_settings.capture(self.settings.record_overhead) {
+                    record_overhead(&root_span, received_at.elapsed());
+                }
+                root_span
+            })
+        };
+        // Measure durations from the creation of the root span, to keep them aligned with the
+        // timestamps of the span itself.
+        request_settings.started_at.set(Instant::now());
+        if let Some(connection) = req.conn_data::<ConnectionMetadata>() {
+            connection.on_request();
+        }
+
+        let payload_tally =
+            if request_settings.capture(self.settings.record_content_length_mismatch) {
+                Some(PayloadTally::wrap(&mut req, request_settings.debug))
+            } else {
+                None
+            };
 
-        let root_span_wrapper = RootSpan::new(root_span.clone());
+        let recorded_fields = match &deferred {
+            Some(buffer) => Some(buffer.clone()),
+            // Disabled spans don't record anything.
+            None if root_span.is_disabled() => None,
+            None => request_settings.recorded_fields.clone(),
+        };
+        let root_span_wrapper = RootSpan::new(root_span.clone(), recorded_fields);
         req.extensions_mut().insert(root_span_wrapper);
 
-        let fut = root_span.in_scope(|| self.service.call(req));
+        let fut = root_span.in_scope(|| request_id.in_scope(|| self.service.call(req)));
 
         TracingResponse {
             fut,
             span: root_span,
+            request_id,
+            started_at: request_settings.started_at.get(),
+            settings: request_settings,
+            busy: Duration::ZERO,
+            completed: false,
+            payload_tally,
+            deferred,
+            _inflight_guard: inflight_guard,
             _root_span_type: std::marker::PhantomData,
         }
     }
 }
 
 #[doc(hidden)]
-#[pin_project::pin_project]
+#[pin_project::pin_project(PinnedDrop)]
 pub struct TracingResponse<F, RootSpanType> {
     #[pin]
     fut: F,
     span: Span,
+    request_id: RequestId,
+    settings: Rc<RequestSettings>,
+    started_at: Instant,
+    // Time spent polling `fut`.
+    busy: Duration,
+    // `fut` has been polled to completion.
+    completed: bool,
+    payload_tally: Option<PayloadTally>,
+    // The values recorded using `RootSpan`, if the creation of the root span has been deferred.
+    deferred: Option<RecordedFields>,
+    // Removes the request from the count of inflight requests when dropped.
+    _inflight_guard: Option<InflightGuard>,
     _root_span_type: std::marker::PhantomData<RootSpanType>,
 }
 
 #[doc(hidden)]
-#[pin_project::pin_project]
+#[pin_project::pin_project(PinnedDrop)]
 pub struct StreamSpan<B> {
     #[pin]
     body: B,
     span: Span,
+    tally: BodyTally,
+}
+
+impl<B> StreamSpan<B> {
+    fn new(
+        body: B,
+        span: Span,
+        settings: &RequestSettings,
+        on_complete: fn(Span, &BodyStats),
+    ) -> Self {
+        Self {
+            body,
+            span,
+            tally: BodyTally {
+                record_bytes_written: settings.capture(settings.settings.record_bytes_written),
+                record_throughput: settings.capture(settings.settings.record_throughput),
+                connection_accepted_at: settings.connection_accepted_at,
+                recorded_fields: settings.recorded_fields.clone(),
+                access_log: settings.settings.access_log.clone(),
+                bytes_written: 0,
+                streaming_since: Instant::now(),
+                completed: false,
+                on_complete,
+            },
+        }
+    }
+}
+
+/// Keeps track of the response body as it is written out.
+struct BodyTally {
+    record_bytes_written: bool,
+    record_throughput: bool,
+    // Set if `net.full_duration_ms` should be recorded.
+    connection_accepted_at: Option<Instant>,
+    recorded_fields: Option<RecordedFields>,
+    access_log: Option<AccessLog>,
+    bytes_written: u64,
+    // When the response head was ready.
+    streaming_since: Instant,
+    completed: bool,
+    // `RootSpanBuilder::on_body_complete`.
+    on_complete: fn(Span, &BodyStats),
+}
+
+impl BodyTally {
+    /// Record the fields that depend on the whole body, at most once.
+    fn complete(&mut self, span: &Span, error: bool) {
+        if self.completed {
+            return;
+        }
+        self.completed = true;
+        RecordedFields::in_scope(self.recorded_fields.as_ref(), || self.record(span, error));
+        if let Some(access_log) = &self.access_log {
+            write_access_log(access_log, span, self.recorded_fields.as_ref());
+        }
+    }
+
+    fn record(&self, span: &Span, error: bool) {
+        let completed_at = Instant::now();
+        if self.record_bytes_written {
+            record(span, "http.response.bytes_written", self.bytes_written);
+        }
+        if self.record_throughput {
+            let elapsed = completed_at.duration_since(self.streaming_since);
+            // Empty and instantaneous bodies have no meaningful throughput.
+            if self.bytes_written > 0 && !elapsed.is_zero() {
+                let throughput = self.bytes_written as f64 / elapsed.as_secs_f64();
+                record(span, "http.response.throughput_bps", throughput as u64);
+            }
+        }
+        if let Some(accepted_at) = self.connection_accepted_at {
+            let full_duration = completed_at.saturating_duration_since(accepted_at);
+            record(span, "net.full_duration_ms", duration_ms(full_duration));
+        }
+        let stats = BodyStats {
+            bytes_written: self.bytes_written,
+            error,
+            completed_at,
+        };
+        span.in_scope(|| (self.on_complete)(span.clone(), &stats));
+    }
 }
 
 impl<F, B, RootSpanType> Future for TracingResponse<F, RootSpanType>
@@ -151,28 +1262,101 @@ where
 
         let fut = this.fut;
         let span = this.span;
+        let request_id = &*this.request_id;
+        let settings = this.settings;
+        let started_at = *this.started_at;
+        let busy = this.busy;
+        let completed = this.completed;
+        let payload_tally = this.payload_tally;
+        let deferred = this.deferred;
 
-        span.in_scope(|| match fut.poll(cx) {
-            Poll::Pending => Poll::Pending,
-            Poll::Ready(outcome) => {
+        let outcome =
+            match span.in_scope(|| request_id.in_scope(|| poll_timed(fut, cx, settings, busy))) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(outcome) => outcome,
+            };
+        *completed = true;
+        let recorded_fields = settings.recorded_fields.as_ref();
+        RecordedFields::in_scope(recorded_fields, || {
+            // Errors don't carry the request: the root span can't be created for them.
+            if let (Some(buffer), Ok(response)) = (deferred.take(), &outcome) {
+                let created_at = Instant::now();
+                let request = ServiceRequest::from_request(response.request().clone());
+                *span = RootSpanType::on_request_start(&request);
+                if settings.capture(settings.settings.record_overhead) {
+                    record_overhead(span, created_at.elapsed());
+                }
+                buffer.replay(span);
+            }
+
+            span.in_scope(|| {
+                // Recorded first, so that they are available to `on_request_end`.
+                record_timings(span, settings, started_at, *busy);
+                if let Some(payload_tally) = payload_tally {
+                    payload_tally.record(span);
+                }
+                if settings.capture(settings.settings.record_outcome) {
+                    record_outcome(span, &outcome);
+                }
                 RootSpanType::on_request_end(Span::current(), &outcome);
+                if let Some(on_error) = &settings.settings.on_error {
+                    notify_error(on_error, &outcome);
+                }
+                if settings.settings.emit_completion_event {
+                    emit_completion_event(&settings.settings.completion_event_levels, &outcome);
+                }
 
                 #[cfg(feature = "emit_event_on_error")]
                 {
                     emit_event_on_error(&outcome);
                 }
 
-                Poll::Ready(outcome.map(|service_response| {
-                    service_response.map_body(|_, body| StreamSpan {
-                        body,
-                        span: span.clone(),
-                    })
-                }))
-            }
+                match outcome {
+                    Ok(service_response) => {
+                        record_streamed(span, service_response.response().body().size());
+                        Poll::Ready(Ok(service_response.map_body(|_, body| {
+                            StreamSpan::new(
+                                body,
+                                span.clone(),
+                                settings,
+                                RootSpanType::on_body_complete,
+                            )
+                        })))
+                    }
+                    Err(error) => {
+                        // There is no body: the request is done.
+                        if let Some(access_log) = &settings.settings.access_log {
+                            write_access_log(access_log, span, recorded_fields);
+                        }
+                        Poll::Ready(Err(error))
+                    }
+                }
+            })
         })
     }
 }
 
+#[pin_project::pinned_drop]
+impl<F, RootSpanType> PinnedDrop for TracingResponse<F, RootSpanType> {
+    fn drop(self: Pin<&mut Self>) {
+        // The request future is dropped before completion if the request is cancelled - e.g. if
+        // the client disconnected or a timeout middleware gave up on it.
+        let this = self.project();
+        if *this.completed {
+            return;
+        }
+        let recorded_fields = this.settings.recorded_fields.as_ref();
+        if this.settings.capture(this.settings.settings.record_outcome) {
+            RecordedFields::in_scope(recorded_fields, || {
+                record(this.span, "http.outcome", "cancelled")
+            });
+        }
+        if let Some(access_log) = &this.settings.settings.access_log {
+            write_access_log(access_log, this.span, recorded_fields);
+        }
+    }
+}
+
 impl<B> MessageBody for StreamSpan<B>
 where
     B: MessageBody,
@@ -191,7 +1375,146 @@ where
 
         let body = this.body;
         let span = this.span;
-        span.in_scope(|| body.poll_next(cx))
+        let poll = span.in_scope(|| body.poll_next(cx));
+        let error = match &poll {
+            Poll::Ready(Some(Ok(chunk))) => {
+                this.tally.bytes_written += chunk.len() as u64;
+                return poll;
+            }
+            Poll::Ready(Some(Err(_))) => true,
+            Poll::Ready(None) => false,
+            Poll::Pending => return poll,
+        };
+        this.tally.complete(span, error);
+        poll
+    }
+}
+
+#[pin_project::pinned_drop]
+impl<B> PinnedDrop for StreamSpan<B> {
+    fn drop(self: Pin<&mut Self>) {
+        // The body might not be polled to completion - e.g. if it is empty or if the client
+        // disconnected.
+        let this = self.project();
+        this.tally.complete(this.span, false);
+    }
+}
+
+/// Bodies that know their size upfront (including empty ones) are considered buffered.
+/// Bodies that report a size but are produced incrementally - e.g. a `SizedStream` - are
+/// therefore recorded as buffered.
+fn record_streamed(span: &Span, size: BodySize) {
+    let streamed = matches!(size, BodySize::Stream);
+    record(span, "http.response.streamed", streamed);
+}
+
+fn poll_timed<F: Future>(
+    fut: Pin<&mut F>,
+    cx: &mut Context<'_>,
+    settings: &RequestSettings,
+    busy: &mut Duration,
+) -> Poll<F::Output> {
+    if !settings.capture(settings.settings.record_busy_time) {
+        return fut.poll(cx);
+    }
+    let start = Instant::now();
+    let poll = fut.poll(cx);
+    *busy += start.elapsed();
+    poll
+}
+
+/// `true` if spans at `level` are disabled. Unknown levels are assumed to be enabled.
+fn is_disabled(level: Option<Level>) -> bool {
+    level.is_some_and(|level| level > LevelFilter::current())
+}
+
+fn record_overhead(span: &Span, overhead: Duration) {
+    record(
+        span,
+        "tracing_actix_web.overhead_us",
+        u64::try_from(overhead.as_micros()).unwrap_or(u64::MAX),
+    );
+}
+
+fn record_timings(span: &Span, settings: &RequestSettings, started_at: Instant, busy: Duration) {
+    let elapsed = started_at.elapsed();
+    if settings.capture(settings.settings.record_duration) {
+        record(span, "http.server.duration_ms", duration_ms(elapsed));
+    }
+    if let Some(deadline) = settings.deadline {
+        // Negative if the deadline has been exceeded.
+        let remaining_ms = duration_ms(deadline) - duration_ms(elapsed);
+        record(span, "http.deadline_remaining_ms", remaining_ms);
+    }
+    if settings.capture(settings.settings.record_busy_time) {
+        record(span, "http.server.busy_ms", duration_ms(busy));
+    }
+}
+
+fn notify_error<B>(on_error: &ErrorCallback, outcome: &Result<ServiceResponse<B>, Error>) {
+    let (response_error, status_code) = match outcome {
+        Ok(response) => match response.response().error() {
+            // use the status code already constructed for the outgoing HTTP response
+            Some(err) => (err.as_response_error(), response.status()),
+            None => return,
+        },
+        Err(error) => {
+            let response_error = error.as_response_error();
+            (response_error, response_error.status_code())
+        }
+    };
+    if status_code >= on_error.threshold {
+        (on_error.callback)(status_code, &response_error.to_string());
+    }
+}
+
+/// Write the values recorded on `span` to the access log, as a JSON line.
+fn write_access_log(access_log: &AccessLog, span: &Span, fields: Option<&RecordedFields>) {
+    let mut line = match fields {
+        // Nothing is recorded on disabled spans.
+        Some(fields) if !span.is_disabled() => fields.to_json(),
+        _ => return,
+    };
+    let timestamp_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64);
+    line.insert("timestamp_ms".to_owned(), timestamp_ms.into());
+    let mut line = serde_json::Value::Object(line).to_string();
+    line.push('\n');
+    // The sink is still usable if a writer panicked.
+    let mut sink = access_log.lock().unwrap_or_else(PoisonError::into_inner);
+    // Access logging is best-effort: a failing sink must not fail the request.
+    let _ = sink.write_all(line.as_bytes());
+}
+
+fn record_outcome<B>(span: &Span, outcome: &Result<ServiceResponse<B>, Error>) {
+    let status_code = match outcome {
+        Ok(response) => response.status(),
+        Err(error) => error.as_response_error().status_code(),
+    };
+    let outcome = if status_code.is_client_error() {
+        "client_error"
+    } else if status_code.is_server_error() || status_code.as_u16() >= 600 {
+        "server_error"
+    } else {
+        "success"
+    };
+    record(span, "http.outcome", outcome);
+}
+
+fn emit_completion_event<B>(levels: &LevelMap, outcome: &Result<ServiceResponse<B>, Error>) {
+    let status_code = match outcome {
+        Ok(response) => response.status(),
+        Err(error) => error.as_response_error().status_code(),
+    };
+    let code = status_code.as_u16();
+    let message = "HTTP request completed";
+    match levels.level_for(status_code) {
+        Level::TRACE => tracing::trace!(http.status_code = code, "{}", message),
+        Level::DEBUG => tracing::debug!(http.status_code = code, "{}", message),
+        Level::INFO => tracing::info!(http.status_code = code, "{}", message),
+        Level::WARN => tracing::warn!(http.status_code = code, "{}", message),
+        Level::ERROR => tracing::error!(http.status_code = code, "{}", message),
     }
 }
 
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::iter::FromIterator;
+
+/// A registry mapping route patterns to [OpenAPI operation ids](https://spec.openapis.org/oas/v3.1.0#fixed-fields-7).
+///
+/// Register `OperationIds` as application data: [`DefaultRootSpanBuilder`] looks up the route
+/// matched by the incoming request and records the corresponding operation id
+/// (`http.route.operation_id`).
+/// Operation ids are stable across refactorings of your routes, making it easier to
+/// correlate telemetry data with other tools built on top of your OpenAPI specification.
+///
+/// Routes are identified using the same (templated) pattern recorded as `http.route`.
+///
+/// ```rust
+/// use actix_web::{web, App, HttpResponse};
+/// use tracing_actix_web::{OperationIds, TracingLogger};
+///
+/// let operation_ids = OperationIds::new()
+///     .register("/users/{user_id}", "getUser")
+///     .register("/users", "listUsers");
+///
+/// let app = App::new()
+///     .wrap(TracingLogger::default())
+///     .app_data(operation_ids)
+///     .route("/users/{user_id}", web::get().to(HttpResponse::Ok))
+///     .route("/users", web::get().to(HttpResponse::Ok));
+/// ```
+///
+/// If your routes are generated from a specification, you can collect an iterator of
+/// `(route, operation id)` pairs into `OperationIds`.
+///
+/// [`DefaultRootSpanBuilder`]: crate::DefaultRootSpanBuilder
+#[derive(Clone, Debug, Default)]
+pub struct OperationIds {
+    ids: HashMap<String, String>,
+}
+
+impl OperationIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Associate `operation_id` to the route matching `pattern`.
+    pub fn register(mut self, pattern: impl Into<String>, operation_id: impl Into<String>) -> Self {
+        self.ids.insert(pattern.into(), operation_id.into());
+        self
+    }
+
+    /// The operation id associated to the route matching `pattern`, if any.
+    pub fn get(&self, pattern: &str) -> Option<&str> {
+        self.ids.get(pattern).map(String::as_str)
+    }
+}
+
+impl<P: Into<String>, I: Into<String>> FromIterator<(P, I)> for OperationIds {
+    fn from_iter<T: IntoIterator<Item = (P, I)>>(iter: T) -> Self {
+        Self {
+            ids: iter
+                .into_iter()
+                .map(|(pattern, id)| (pattern.into(), id.into()))
+                .collect(),
+        }
+    }
+}
@@ -51,5 +51,5 @@ pub(crate) fn set_otel_parent(req: &ServiceRequest, span: &tracing::Span) {
     // If we have a remote parent span, this will be the parent's trace identifier.
     // If not, it will be the newly generated trace identifier with this request as root span.
     let trace_id = span.context().span().span_context().trace_id().to_hex();
-    span.record("trace_id", &tracing::field::display(trace_id));
+    crate::fields::record(span, "trace_id", &tracing::field::display(trace_id));
 }
@@ -0,0 +1,38 @@
+/// The status of the OpenTelemetry span associated to the root span (`otel.status_code`).
+///
+/// Use it in your custom [`RootSpanBuilder`] instead of string literals:
+///
+/// ```rust
+/// use tracing_actix_web::OtelStatus;
+///
+/// let span = tracing::info_span!("HTTP request", otel.status_code = tracing::field::Empty);
+/// span.record("otel.status_code", OtelStatus::Error.as_str());
+/// ```
+///
+/// [`RootSpanBuilder`]: crate::RootSpanBuilder
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OtelStatus {
+    /// The default status - the span has not been classified as successful or failed.
+    Unset,
+    /// The operation completed successfully.
+    Ok,
+    /// The operation failed.
+    Error,
+}
+
+impl OtelStatus {
+    /// The representation of the status expected by OpenTelemetry (`UNSET`, `OK` or `ERROR`).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OtelStatus::Unset => "UNSET",
+            OtelStatus::Ok => "OK",
+            OtelStatus::Error => "ERROR",
+        }
+    }
+}
+
+impl std::fmt::Display for OtelStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,105 @@
+use crate::fields::record;
+use actix_web::dev::{Payload, ServiceRequest};
+use actix_web::error::PayloadError;
+use actix_web::http::header::CONTENT_LENGTH;
+use actix_web::web::Bytes;
+use actix_web::HttpMessage;
+use futures_core::Stream;
+use std::cell::{Cell, RefCell};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use tracing::Span;
+
+/// How many bytes of the request body are captured for requests flagged for debugging.
+pub(crate) const BODY_PREFIX_LEN: usize = 1024;
+
+/// Tally of the bytes read from the body of a request, compared against its `Content-Length`.
+pub(crate) struct PayloadTally {
+    declared: Option<u64>,
+    read: Rc<Cell<u64>>,
+    // The payload has been read until its end (or until it failed).
+    complete: Rc<Cell<bool>>,
+    // The first `BODY_PREFIX_LEN` bytes read, if they are being captured.
+    prefix: Option<Rc<RefCell<Vec<u8>>>>,
+}
+
+impl PayloadTally {
+    /// Replace the payload of `request` with a wrapper that counts the bytes read from it and, if
+    /// `capture_prefix` is set, keeps a copy of the first ones.
+    pub(crate) fn wrap(request: &mut ServiceRequest, capture_prefix: bool) -> Self {
+        let declared = request
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok());
+        let tally = Self {
+            declared,
+            read: Rc::new(Cell::new(0)),
+            complete: Rc::new(Cell::new(false)),
+            prefix: Some(Rc::default()).filter(|_| capture_prefix),
+        };
+        let payload = CountingPayload {
+            payload: request.take_payload(),
+            read: tally.read.clone(),
+            complete: tally.complete.clone(),
+            prefix: tally.prefix.clone(),
+        };
+        let payload: Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>> = Box::pin(payload);
+        request.set_payload(payload.into());
+        tally
+    }
+
+    pub(crate) fn record(&self, span: &Span) {
+        let read = self.read.get();
+        record(span, "http.request.body_bytes_read", read);
+        if let Some(prefix) = &self.prefix {
+            let prefix = prefix.borrow();
+            if !prefix.is_empty() {
+                let prefix = String::from_utf8_lossy(&prefix);
+                record(span, "http.request.body_prefix", prefix.as_ref());
+            }
+        }
+        let declared = match self.declared {
+            Some(declared) => declared,
+            None => return,
+        };
+        // If the body has not been read in full, we can only tell if the client sent more bytes
+        // than it declared.
+        if self.complete.get() || read > declared {
+            record(
+                span,
+                "http.request.content_length_mismatch",
+                read != declared,
+            );
+        }
+    }
+}
+
+struct CountingPayload {
+    payload: Payload,
+    read: Rc<Cell<u64>>,
+    complete: Rc<Cell<bool>>,
+    prefix: Option<Rc<RefCell<Vec<u8>>>>,
+}
+
+impl Stream for CountingPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = Pin::new(&mut self.payload).poll_next(cx);
+        match &poll {
+            Poll::Ready(Some(Ok(chunk))) => {
+                self.read.set(self.read.get() + chunk.len() as u64);
+                if let Some(prefix) = &self.prefix {
+                    let mut prefix = prefix.borrow_mut();
+                    let missing = BODY_PREFIX_LEN.saturating_sub(prefix.len());
+                    prefix.extend_from_slice(&chunk[..missing.min(chunk.len())]);
+                }
+            }
+            Poll::Ready(Some(Err(_))) | Poll::Ready(None) => self.complete.set(true),
+            Poll::Pending => {}
+        }
+        poll
+    }
+}
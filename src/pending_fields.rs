@@ -0,0 +1,98 @@
+use crate::fields::record;
+use actix_web::HttpMessage;
+use std::borrow::Cow;
+use tracing::Span;
+
+/// Field values contributed to the root span by middlewares that run before [`TracingLogger`].
+///
+/// Middlewares registered after `TracingLogger` (i.e. wrapping it) process the incoming request
+/// before the root span has been created: they can't record fields on it directly.
+/// They can instead store their values in request-local storage using
+/// [`PendingSpanFields::record`] - the root span created by [`root_span!`] drains them and
+/// records them as soon as it is created:
+///
+/// - values for fields declared by the root span (e.g. a custom field you passed to
+///   [`root_span!`] in your [`RootSpanBuilder`]) are recorded in the corresponding field.
+///   This includes the fields populated by `tracing-actix-web` when the request comes in: their
+///   value is overwritten;
+/// - all other values are recorded in the `pending_fields` field, as a JSON object.
+///
+/// ```rust
+/// use actix_web::dev::Service;
+/// use actix_web::App;
+/// use tracing_actix_web::{PendingSpanFields, TracingLogger};
+///
+/// let app = App::new()
+///     .wrap(TracingLogger::default())
+///     // Registered after `TracingLogger`, therefore it runs before it
+///     .wrap_fn(|req, srv| {
+///         PendingSpanFields::record(&req, "tenant_id", "acme");
+///         srv.call(req)
+///     });
+/// ```
+///
+/// [`TracingLogger`]: crate::TracingLogger
+/// [`root_span!`]: crate::root_span!
+/// [`RootSpanBuilder`]: crate::RootSpanBuilder
+#[derive(Clone, Debug, Default)]
+pub struct PendingSpanFields {
+    fields: Vec<(Cow<'static, str>, String)>,
+}
+
+impl PendingSpanFields {
+    /// Store `value` for the field called `name` in the request-local storage of `request`.
+    ///
+    /// If a value has already been stored for `name`, it is replaced.
+    pub fn record<R: HttpMessage>(
+        request: &R,
+        name: impl Into<Cow<'static, str>>,
+        value: impl ToString,
+    ) {
+        let name = name.into();
+        let value = value.to_string();
+        let mut extensions = request.extensions_mut();
+        if extensions.get::<PendingSpanFields>().is_none() {
+            extensions.insert(PendingSpanFields::default());
+        }
+        let fields = &mut extensions.get_mut::<PendingSpanFields>().unwrap().fields;
+        match fields.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, v)) => *v = value,
+            None => fields.push((name, value)),
+        }
+    }
+
+    /// Iterate over the stored values, in insertion order.
+    ///
+    /// Each item is a tuple containing the name of the field and its value.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields
+            .iter()
+            .map(|(name, value)| (name.as_ref(), value.as_str()))
+    }
+
+    /// Remove the pending fields from the request-local storage of `request` and record them
+    /// on `span`.
+    pub(crate) fn drain<R: HttpMessage>(request: &R, span: &Span) {
+        let pending = match request.extensions_mut().remove::<PendingSpanFields>() {
+            Some(pending) => pending,
+            None => return,
+        };
+        let metadata = match span.metadata() {
+            Some(metadata) => metadata,
+            // The span is disabled, nothing to record.
+            None => return,
+        };
+        let mut undeclared = serde_json::Map::new();
+        for (name, value) in pending.iter() {
+            if metadata.fields().field(name).is_some() {
+                record(span, name, value);
+            } else {
+                undeclared.insert(name.to_owned(), value.into());
+            }
+        }
+        if !undeclared.is_empty() {
+            let undeclared = serde_json::Value::Object(undeclared).to_string();
+            record(span, "pending_fields", undeclared.as_str());
+        }
+    }
+}
@@ -1,8 +1,15 @@
 use actix_web::{dev::Payload, HttpMessage};
 use actix_web::{FromRequest, HttpRequest, ResponseError};
+use std::cell::RefCell;
 use std::future::{ready, Ready};
+use std::sync::Arc;
 use uuid::Uuid;
 
+thread_local! {
+    // The request id of the request whose processing is currently being polled on this thread.
+    static CURRENT_REQUEST_ID: RefCell<Option<RequestId>> = const { RefCell::new(None) };
+}
+
 /// A unique identifier generated for each incoming request.
 ///
 /// Extracting a `RequestId` when the `TracingLogger` middleware is not registered will result in
@@ -10,27 +17,81 @@ use uuid::Uuid;
 ///
 /// # Usage
 /// ```rust
-/// use actix_web::get;
 /// use tracing_actix_web::RequestId;
 /// use uuid::Uuid;
 ///
-/// #[get("/")]
 /// async fn index(request_id: RequestId) -> String {
 ///   format!("{}", request_id)
 /// }
 ///
-/// #[get("/2")]
 /// async fn index2(request_id: RequestId) -> String {
 ///  let uuid: Uuid = request_id.into();
 ///   format!("{}", uuid)
 /// }
 /// ```
-#[derive(Clone, Copy, Debug)]
-pub struct RequestId(Uuid);
+///
+/// # Prefix
+///
+/// If you configured a [prefix](crate::TracingLogger::request_id_prefix), it is prepended to the
+/// identifier when a `RequestId` is formatted using `Display` - i.e. in the `request_id` field of
+/// the root span and in the body of [`WithRequestId`](crate::WithRequestId) error responses.
+///
+/// The [`Uuid`] you get using `Deref` or `Into` does not include it: a [`Uuid`] can't hold a
+/// prefix. Format the `RequestId` itself, not the [`Uuid`], whenever the value must match the one
+/// recorded in your traces (e.g. in a response header):
+///
+/// ```rust
+/// use tracing_actix_web::RequestId;
+/// use uuid::Uuid;
+///
+/// async fn index(request_id: RequestId) -> String {
+///     // With the `svcA-` prefix: `svcA-67e55044-10b1-426f-9247-bb680e5fe0c8`
+///     let displayed = request_id.to_string();
+///     // `67e55044-10b1-426f-9247-bb680e5fe0c8`, in both cases
+///     let uuid: Uuid = request_id.clone().into();
+///     assert_eq!(*request_id, uuid);
+///     displayed
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RequestId {
+    id: Uuid,
+    prefix: Option<Arc<str>>,
+}
 
 impl RequestId {
     pub(crate) fn generate() -> Self {
-        Self(Uuid::new_v4())
+        Self {
+            id: Uuid::new_v4(),
+            prefix: None,
+        }
+    }
+
+    pub(crate) fn with_prefix(mut self, prefix: Option<Arc<str>>) -> Self {
+        self.prefix = prefix;
+        self
+    }
+
+    /// The request id of the request that [`TracingLogger`] is currently processing on this
+    /// thread, if any.
+    ///
+    /// [`TracingLogger`]: crate::TracingLogger
+    pub(crate) fn current() -> Option<Self> {
+        CURRENT_REQUEST_ID.with(|current| current.borrow().clone())
+    }
+
+    /// Execute `f` with `self` set as the [current](RequestId::current) request id.
+    pub(crate) fn in_scope<F: FnOnce() -> T, T>(&self, f: F) -> T {
+        struct Reset(Option<RequestId>);
+
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                CURRENT_REQUEST_ID.with(|current| *current.borrow_mut() = self.0.take());
+            }
+        }
+
+        let _reset = Reset(CURRENT_REQUEST_ID.with(|current| current.replace(Some(self.clone()))));
+        f()
     }
 }
 
@@ -38,19 +99,22 @@ impl std::ops::Deref for RequestId {
     type Target = Uuid;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.id
     }
 }
 
 impl From<RequestId> for Uuid {
     fn from(r: RequestId) -> Self {
-        r.0
+        r.id
     }
 }
 
 impl std::fmt::Display for RequestId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        match &self.prefix {
+            Some(prefix) => write!(f, "{}{}", prefix, self.id),
+            None => write!(f, "{}", self.id),
+        }
     }
 }
 
@@ -62,7 +126,7 @@ impl FromRequest for RequestId {
         ready(
             req.extensions()
                 .get::<RequestId>()
-                .copied()
+                .cloned()
                 .ok_or(RequestIdExtractionError { _priv: () }),
         )
     }
@@ -98,3 +162,35 @@ impl std::fmt::Display for RequestIdExtractionError {
 }
 
 impl std::error::Error for RequestIdExtractionError {}
+
+/// Error returned by [`TracingLogger::request_id_prefix`] when the prefix contains characters
+/// that are not printable ASCII characters.
+///
+/// [`TracingLogger::request_id_prefix`]: crate::TracingLogger::request_id_prefix
+#[derive(Debug)]
+pub struct InvalidRequestIdPrefix {
+    prefix: Arc<str>,
+}
+
+impl InvalidRequestIdPrefix {
+    pub(crate) fn new(prefix: Arc<str>) -> Self {
+        Self { prefix }
+    }
+
+    /// The rejected prefix.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+}
+
+impl std::fmt::Display for InvalidRequestIdPrefix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The request id prefix must only contain printable ASCII characters, got {:?}.",
+            self.prefix
+        )
+    }
+}
+
+impl std::error::Error for InvalidRequestIdPrefix {}
@@ -1,8 +1,12 @@
 use actix_web::{dev::Payload, HttpMessage};
 use actix_web::{FromRequest, HttpRequest, ResponseError};
 use std::future::{ready, Ready};
+use std::sync::{Arc, Mutex, PoisonError};
+use tracing::field::Value;
 use tracing::Span;
 
+use crate::fields::{self, RecordedFields};
+
 #[derive(Clone)]
 /// The root span associated to the in-flight current request.
 ///
@@ -14,21 +18,98 @@ use tracing::Span;
 ///
 /// # Usage
 /// ```rust
-/// use actix_web::get;
 /// use tracing_actix_web::RootSpan;
 /// use uuid::Uuid;
 ///
-/// #[get("/")]
 /// async fn index(root_span: RootSpan) -> String {
 ///     root_span.record("route", &"/");
 ///     # "Hello".to_string()
 /// }
 /// ```
-pub struct RootSpan(Span);
+pub struct RootSpan(
+    Span,
+    Arc<Mutex<Vec<(String, String)>>>,
+    // Where to keep a copy of the recorded values, if they are needed - or the values themselves,
+    // if the creation of the root span has been deferred.
+    Option<RecordedFields>,
+);
 
 impl RootSpan {
-    pub(crate) fn new(span: Span) -> Self {
-        Self(span)
+    pub(crate) fn new(span: Span, recorded_fields: Option<RecordedFields>) -> Self {
+        Self(span, Default::default(), recorded_fields)
+    }
+
+    /// Record `value` for the field called `field`, like [`Span::record`].
+    ///
+    /// Prefer it over recording on the span directly (e.g. via `Span::current()`): the value is
+    /// also seen by the [wide event](crate::WideEventRootSpanBuilder) and the
+    /// [JSON access log](crate::TracingLogger::json_access_log), if enabled, and it is not lost if
+    /// the [creation of the root span is deferred](crate::TracingLogger::lazy_root_span).
+    pub fn record<V: Value>(&self, field: &str, value: V) -> &Self {
+        self.0.record(field, &value);
+        match &self.2 {
+            // The creation of the root span has been deferred: the value is recorded once it
+            // has been created.
+            Some(recorded_fields) if self.0.is_disabled() => recorded_fields.insert(field, &value),
+            Some(recorded_fields) => fields::copy(&self.0, recorded_fields, field, &value),
+            None => {}
+        }
+        self
+    }
+
+    /// Record the evaluation of the feature flag called `name` against the root span.
+    ///
+    /// All evaluations are accumulated in the `feature_flags` field, as a comma-separated
+    /// list of `<name>=<variant>` pairs (e.g. `new-checkout=on,pricing=variant-b`).
+    /// If the same flag is evaluated more than once, the last variant wins.
+    ///
+    /// ```rust
+    /// use tracing_actix_web::RootSpan;
+    ///
+    /// async fn checkout(root_span: RootSpan) -> String {
+    ///     let variant = "on"; // Evaluate your flag
+    ///     root_span.record_flag("new-checkout", variant);
+    ///     # "Hello".to_string()
+    /// }
+    /// ```
+    ///
+    /// # Cardinality
+    ///
+    /// `feature_flags` is as high-cardinality as the combinations of flags (and variants) your
+    /// handlers evaluate. Only record the flags that are relevant to understand the behaviour of
+    /// a request - e.g. the ones driving an experiment - rather than every flag you evaluate.
+    ///
+    /// `feature_flags` is declared by [`root_span!`] - you don't need to declare it yourself if you
+    /// are using it in your custom [`RootSpanBuilder`].
+    ///
+    /// [`root_span!`]: crate::root_span!
+    /// [`RootSpanBuilder`]: crate::RootSpanBuilder
+    pub fn record_flag(&self, name: impl Into<String>, variant: impl Into<String>) {
+        let name = name.into();
+        let variant = variant.into();
+        let mut flags = self.1.lock().unwrap_or_else(PoisonError::into_inner);
+        match flags.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, v)) => *v = variant,
+            None => flags.push((name, variant)),
+        }
+        std::mem::drop(flags);
+        if let Some(flags) = self.flags() {
+            self.record("feature_flags", flags.as_str());
+        }
+    }
+
+    /// The feature flag evaluations recorded so far, formatted as the `feature_flags` field.
+    fn flags(&self) -> Option<String> {
+        let flags = self.1.lock().unwrap_or_else(PoisonError::into_inner);
+        if flags.is_empty() {
+            return None;
+        }
+        let flags = flags
+            .iter()
+            .map(|(name, variant)| format!("{}={}", name, variant))
+            .collect::<Vec<_>>()
+            .join(",");
+        Some(flags)
     }
 }
 
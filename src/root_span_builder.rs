@@ -2,17 +2,109 @@ use crate::{
     debug_root_span, error_root_span, info_root_span, root_span, trace_root_span, warn_root_span,
 };
 use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::ETAG;
 use actix_web::http::StatusCode;
-use actix_web::{Error, ResponseError};
-use tracing::Span;
+use actix_web::{Error, HttpMessage, HttpRequest, ResponseError};
+use serde_json::Value as JsonValue;
+use std::time::Instant;
+use tracing::{Level, Span};
+
+use crate::fields::{record, RecordedFields};
+use crate::root_span_macro::private::{entity_tag, server_timing};
+use crate::settings::{AppDataProbe, RequestSettings};
+use crate::validation::ValidationError;
+use crate::{CorsOutcome, GuardDecisions, IdempotencyOutcome, OperationIds, OtelStatus};
 
 /// `RootSpanBuilder` allows you to customize the root span attached by
 /// [`TracingLogger`] to incoming requests.
 ///
+/// # Two-phase model
+///
+/// The root span is populated in two phases:
+///
+/// - [`on_request_end`](RootSpanBuilder::on_request_end) is invoked as soon as the response head
+///   (status code and headers) is ready, before any byte of its body is sent to the client;
+/// - [`on_body_complete`](RootSpanBuilder::on_body_complete) is invoked once the response body has
+///   been fully sent, has failed or has been dropped (e.g. because the client disconnected).
+///
+/// For buffered responses the two phases are close together, but a streamed body can take
+/// arbitrarily long to be sent after `on_request_end`.
+/// The root span is closed after `on_body_complete` has returned.
+///
+/// ```rust
+/// use actix_web::dev::{ServiceRequest, ServiceResponse};
+/// use actix_web::Error;
+/// use tracing::Span;
+/// use tracing_actix_web::{BodyStats, DefaultRootSpanBuilder, RootSpanBuilder};
+///
+/// pub struct BodyAwareRootSpanBuilder;
+///
+/// impl RootSpanBuilder for BodyAwareRootSpanBuilder {
+///     fn on_request_start(request: &ServiceRequest) -> Span {
+///         tracing_actix_web::root_span!(request, body.failed = tracing::field::Empty)
+///     }
+///
+///     fn on_request_end<B>(span: Span, outcome: &Result<ServiceResponse<B>, Error>) {
+///         DefaultRootSpanBuilder::on_request_end(span, outcome);
+///     }
+///
+///     fn on_body_complete(span: Span, stats: &BodyStats) {
+///         span.record("body.failed", stats.is_error());
+///     }
+/// }
+/// ```
+///
 /// [`TracingLogger`]: crate::TracingLogger
 pub trait RootSpanBuilder {
     fn on_request_start(request: &ServiceRequest) -> Span;
     fn on_request_end<B>(span: Span, outcome: &Result<ServiceResponse<B>, Error>);
+
+    /// Invoked once the response body has been fully sent, has failed or has been dropped.
+    ///
+    /// It is not invoked if the request failed, since there is no response body to send.
+    /// It does nothing by default.
+    fn on_body_complete(_span: Span, _stats: &BodyStats) {}
+
+    /// The level of the spans returned by [`on_request_start`](RootSpanBuilder::on_request_start),
+    /// if it is known upfront.
+    ///
+    /// If the [creation of the root span is deferred](crate::TracingLogger::lazy_root_span) and
+    /// spans at this level are disabled, requests are not traced at all.
+    /// It is `None` by default.
+    const LEVEL: Option<Level> = None;
+
+    #[doc(hidden)]
+    // Keep a copy of the values recorded on the root span (see `RecordedFields`).
+    // It is not part of the public interface of `tracing-actix-web`.
+    const RECORD_FIELDS: bool = false;
+}
+
+/// Statistics about the response body, passed to [`RootSpanBuilder::on_body_complete`].
+#[derive(Clone, Copy, Debug)]
+pub struct BodyStats {
+    pub(crate) bytes_written: u64,
+    pub(crate) error: bool,
+    pub(crate) completed_at: Instant,
+}
+
+impl BodyStats {
+    /// The number of bytes of the response body sent to the client.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// `true` if producing the response body failed.
+    ///
+    /// A body dropped before being fully sent (e.g. because the client disconnected) is not
+    /// considered to have failed.
+    pub fn is_error(&self) -> bool {
+        self.error
+    }
+
+    /// When the response body was complete.
+    pub fn completed_at(&self) -> Instant {
+        self.completed_at
+    }
 }
 
 /// The default [`RootSpanBuilder`] for [`TracingLogger`].
@@ -20,26 +112,105 @@ pub trait RootSpanBuilder {
 /// It logs all captures at the info level.
 ///
 /// It captures:
-/// - HTTP method (`http.method`);
-/// - HTTP route (`http.route`), with templated parameters;
+/// - HTTP method (`http.method`). Non-standard methods can be [normalized](crate::TracingLogger::normalize_http_method)
+///   to `_OTHER`, with the original method recorded as `http.method_raw`;
+/// - HTTP route (`http.route`), with templated parameters. Unmatched routes are recorded according to the [`UnmatchedRoutePolicy`](crate::UnmatchedRoutePolicy);
+/// - The [version of your API](crate::ApiVersionSource) targeted by the request (`http.api_version`), if configured;
 /// - HTTP version (`http.flavor`);
-/// - HTTP host (`http.host`);
-/// - Client IP (`http.client_ip`);
-/// - User agent (`http.user_agent`);
+/// - HTTP host (`http.host`), unless the `field-host` feature is disabled;
+/// - Whether the HTTP host is one of the [allowed hosts](crate::TracingLogger::allowed_host) (`http.host.allowed`), if configured;
+/// - Client IP (`http.client_ip`), unless the `field-client-ip` feature is disabled;
+/// - User agent (`http.user_agent`), unless the `field-user-agent` feature is disabled;
 /// - Request path (`http.target`);
 /// - Status code (`http.status_code`);
-/// - [Request id](crate::RequestId) (`request_id`);
+/// - [Coarse outcome](crate::TracingLogger::record_outcome) of the request (`http.outcome`), if enabled;
+/// - [Size of the response body](crate::TracingLogger::record_bytes_written) (`http.response.bytes_written`), if enabled;
+/// - [Rate at which the response body was written out](crate::TracingLogger::record_throughput)
+///   (`http.response.throughput_bps`), if enabled;
+/// - The [`ETag` header of the response](crate::TracingLogger::record_etag) (`http.response.etag`), if enabled;
+/// - The [`Server-Timing` header of the response](crate::TracingLogger::record_server_timing)
+///   (`http.response.server_timing`), if enabled;
+/// - Whether the response body is streamed (`http.response.streamed`). A body is considered streamed if its
+///   size is not known upfront: bodies with a known size - including empty bodies and sized streams - are
+///   recorded as buffered;
+/// - The [outcome of CORS checks](crate::CorsOutcome) (`http.cors.allowed` and `http.cors.origin`), if recorded;
+/// - [Feature flag evaluations](crate::RootSpan::record_flag) (`feature_flags`), if any;
+/// - Values [contributed by outer middlewares](crate::PendingSpanFields) for undeclared fields (`pending_fields`), if any;
+/// - The [requested byte range](crate::TracingLogger::record_range) (`http.request.range`), if enabled;
+/// - The [transfer encoding of the request body](crate::TracingLogger::record_transfer_encoding)
+///   (`http.request.transfer_encoding`), if enabled;
+/// - The [`Max-Forwards` header](crate::TracingLogger::record_max_forwards) (`http.request.max_forwards`), if enabled;
+/// - The number of bytes read from the request body (`http.request.body_bytes_read`) and whether it
+///   [differs from `Content-Length`](crate::TracingLogger::record_content_length_mismatch)
+///   (`http.request.content_length_mismatch`), if enabled;
+/// - The [idempotency key](crate::TracingLogger::record_idempotency_key) of the request (`http.idempotency.key`),
+///   if enabled, and whether it was [served by replaying a stored result](crate::IdempotencyOutcome)
+///   (`http.idempotency.replayed`), if recorded;
+/// - Whether the request carries an [`Authorization` header](crate::TracingLogger::record_authenticated)
+///   (`http.request.authenticated`), if enabled;
+/// - Whether the request is [conditional](crate::TracingLogger::record_conditional) (`http.request.conditional`)
+///   and its `If-None-Match` header (`http.request.if_none_match`), if enabled;
+/// - Request headers (`http.request.headers`), query string (`http.request.query`) and the first KiB of the request
+///   body (`http.request.body_prefix`), only for requests flagged for [debugging](crate::TracingLogger::debug_secret);
+/// - The [probed application data](crate::TracingLogger::probe_app_data) that was available (`http.app_data.types`)
+///   or missing (`http.app_data.missing`), only for requests flagged for debugging;
+/// - The [duration](crate::TracingLogger::record_duration) of the request processing (`http.server.duration_ms`)
+///   and the time spent [actively processing it](crate::TracingLogger::record_busy_time) (`http.server.busy_ms`), if enabled;
+/// - The [deadline advertised by the client](crate::TracingLogger::record_deadline) (`http.deadline_ms`) and how much
+///   of it was left when the response was ready (`http.deadline_remaining_ms`), if enabled;
+/// - The [number of requests being processed](crate::TracingLogger::record_inflight_requests) when the request came in
+///   (`server.inflight_requests`), if enabled;
+/// - The time elapsed between the connection being handed over to a worker and the start of its first request
+///   (`net.time_to_first_request_ms`), if [`on_connect`](crate::on_connect) is registered;
+/// - Whether the connection was reused (`net.connection.reused`), if [`on_connect`](crate::on_connect) is registered;
+/// - The [time elapsed since the connection was accepted](crate::TracingLogger::record_full_duration) when the
+///   response to its first request is complete (`net.full_duration_ms`), if enabled;
+/// - The [JA3](crate::TracingLogger::record_ja3) (`tls.ja3`) and [JA4](crate::TracingLogger::record_ja4) (`tls.ja4`)
+///   fingerprints of the client, if configured;
+/// - The [channel](crate::TracingLogger::classify_channel) the request comes from (`client.channel`), if classified;
+/// - Whether the TLS session was resumed (`tls.resumed`) and how long the TLS handshake took (`tls.handshake.duration_ms`),
+///   if [`TlsConnectionInfo`](crate::TlsConnectionInfo) is available;
+/// - [OpenAPI operation id](crate::OperationIds) (`http.route.operation_id`), if registered;
+/// - [Cost of the request](crate::TracingLogger::route_cost) (`http.request.cost`), if configured;
+/// - Decisions taken by [traced guards](crate::TracedGuard) (`http.route.guard.matched` and `http.route.guard.rejected`), if any;
+/// - The number of parts in a multipart upload (`http.request.multipart_part_count`), the identifier of the
+///   credential used to authenticate the request (`enduser.credential_id`), whether the request opened a
+///   database transaction (`db.transaction`) and whether the handler served a memoized result (`handler.memoized`),
+///   if [recorded by your handlers or middlewares](crate#pre-declared-fields);
+/// - The [time spent creating the root span](crate::TracingLogger::record_overhead) (`tracing_actix_web.overhead_us`),
+///   if enabled;
+/// - Whether the request comes from a [verbose client](crate::TracingLogger::verbose_for) (`tracing_actix_web.verbose`),
+///   only recorded when it does;
 /// - `Display` (`exception.message`) and `Debug` (`exception.details`) representations of the error, if there was an error;
+/// - The extractor that rejected the request (`validation.extractor`) and the offending field (`validation.field`),
+///   if the request failed [validation](#validation-errors);
 /// - [Request id](crate::RequestId) (`request_id`);
 /// - [OpenTelemetry trace identifier](https://github.com/open-telemetry/opentelemetry-specification/blob/main/specification/overview.md#spancontext) (`trace_id`). Empty if the feature is not enabled;
-/// - OpenTelemetry span kind, set to `server` (`otel.kind`).
+/// - OpenTelemetry span kind, set to `server` (`otel.kind`);
+/// - OpenTelemetry [status](crate::OtelStatus) (`otel.status_code`), `OK` unless the request failed with a server error.
 ///
 /// All field names follow [OpenTelemetry's semantic convention](https://github.com/open-telemetry/opentelemetry-specification/tree/main/specification/trace/semantic_conventions).
 ///
+/// # Validation errors
+///
+/// The errors returned by the `Json`, `Path`, `Query` and `Form` extractors of `actix-web` are
+/// recognised - both as they are returned by `actix-web` and wrapped in an
+/// [`InternalError`](actix_web::error::InternalError) by a custom error handler:
+///
+/// - `validation.extractor` is set to the name of the extractor (e.g. `Json`);
+/// - `validation.field` is set to the name of the offending field, if the error is about a missing,
+///   unknown or duplicate field. It is not recorded for other errors (e.g. a value of the wrong type):
+///   check `exception.message` for the details.
+///
+/// Errors returned by other extractors, or converted into a different error type by a custom error
+/// handler, are not recognised.
+///
 /// [`TracingLogger`]: crate::TracingLogger
 pub struct DefaultRootSpanBuilder;
 
 impl RootSpanBuilder for DefaultRootSpanBuilder {
+    const LEVEL: Option<Level> = Some(Level::INFO);
+
     fn on_request_start(request: &ServiceRequest) -> Span {
         root_span!(request)
     }
@@ -47,16 +218,33 @@ impl RootSpanBuilder for DefaultRootSpanBuilder {
     fn on_request_end<B>(span: Span, outcome: &Result<ServiceResponse<B>, Error>) {
         match &outcome {
             Ok(response) => {
+                let extensions = response.request().extensions();
+                if let Some(decisions) = extensions.get::<GuardDecisions>() {
+                    record_guard_decisions(&span, decisions);
+                }
+                if let Some(cors) = extensions.get::<CorsOutcome>() {
+                    record_cors_outcome(&span, cors);
+                }
+                if let Some(idempotency) = extensions.get::<IdempotencyOutcome>() {
+                    record(&span, "http.idempotency.replayed", idempotency.replayed());
+                }
+                std::mem::drop(extensions);
+                record_etag(&span, response);
+                record_server_timing(&span, response);
+                record_app_data_probes(&span, response.request());
+                record_operation_id(&span, response.request());
                 if let Some(error) = response.response().error() {
+                    record_validation_error(&span, error);
                     // use the status code already constructed for the outgoing HTTP response
                     handle_error(span, response.status(), error.as_response_error());
                 } else {
                     let code: i32 = response.response().status().as_u16().into();
-                    span.record("http.status_code", &code);
-                    span.record("otel.status_code", &"OK");
+                    record(&span, "http.status_code", code);
+                    record(&span, "otel.status_code", OtelStatus::Ok.as_str());
                 }
             }
             Err(error) => {
+                record_validation_error(&span, error);
                 let response_error = error.as_response_error();
                 handle_error(span, response_error.status_code(), response_error);
             }
@@ -79,6 +267,8 @@ impl RootSpanBuilder for DefaultRootSpanBuilder {
 pub struct TraceRootSpanBuilder;
 
 impl RootSpanBuilder for TraceRootSpanBuilder {
+    const LEVEL: Option<Level> = Some(Level::TRACE);
+
     fn on_request_start(request: &ServiceRequest) -> Span {
         trace_root_span!(request)
     }
@@ -103,6 +293,8 @@ impl RootSpanBuilder for TraceRootSpanBuilder {
 pub struct DebugRootSpanBuilder;
 
 impl RootSpanBuilder for DebugRootSpanBuilder {
+    const LEVEL: Option<Level> = Some(Level::DEBUG);
+
     fn on_request_start(request: &ServiceRequest) -> Span {
         debug_root_span!(request)
     }
@@ -127,6 +319,8 @@ impl RootSpanBuilder for DebugRootSpanBuilder {
 pub struct InfoRootSpanBuilder;
 
 impl RootSpanBuilder for InfoRootSpanBuilder {
+    const LEVEL: Option<Level> = Some(Level::INFO);
+
     fn on_request_start(request: &ServiceRequest) -> Span {
         info_root_span!(request)
     }
@@ -151,6 +345,8 @@ impl RootSpanBuilder for InfoRootSpanBuilder {
 pub struct WarnRootSpanBuilder;
 
 impl RootSpanBuilder for WarnRootSpanBuilder {
+    const LEVEL: Option<Level> = Some(Level::WARN);
+
     fn on_request_start(request: &ServiceRequest) -> Span {
         warn_root_span!(request)
     }
@@ -175,6 +371,8 @@ impl RootSpanBuilder for WarnRootSpanBuilder {
 pub struct ErrorRootSpanBuilder;
 
 impl RootSpanBuilder for ErrorRootSpanBuilder {
+    const LEVEL: Option<Level> = Some(Level::ERROR);
+
     fn on_request_start(request: &ServiceRequest) -> Span {
         error_root_span!(request)
     }
@@ -184,19 +382,271 @@ impl RootSpanBuilder for ErrorRootSpanBuilder {
     }
 }
 
+/// A [`RootSpanBuilder`] for [`TracingLogger`] tailored to the "wide event" model favoured by
+/// observability backends such as [Honeycomb](https://www.honeycomb.io/).
+///
+/// It creates the same root span of [`DefaultRootSpanBuilder`] but, when the request has been
+/// processed, it also emits a single info-level event (named `HTTP request`) carrying all the fields
+/// recorded on the root span, flattened - you don't need to stitch together the span and its
+/// events to get the full picture.
+///
+/// The event has one field for each field declared by [`root_span!`](crate::root_span!), with the
+/// value recorded on the root span - fields that have not been recorded are left out.
+/// The values recorded by your handlers are included as long as you record them using
+/// [`RootSpan::record`](crate::RootSpan::record): values recorded on the span directly (e.g.
+/// via `Span::current()`) are not seen by the event.
+///
+/// The event is emitted once the response body has been sent to the client (or once the request
+/// has failed, if there is no response), therefore it includes the fields computed on body
+/// completion, like `http.response.bytes_written`.
+/// No event is emitted if the root span is disabled (e.g. filtered out by your subscriber).
+///
+/// ```rust
+/// # use tracing_actix_web::{TracingLogger, WideEventRootSpanBuilder};
+/// let logger = TracingLogger::<WideEventRootSpanBuilder>::new();
+/// ```
+///
+/// [`TracingLogger`]: crate::TracingLogger
+pub struct WideEventRootSpanBuilder;
+
+impl RootSpanBuilder for WideEventRootSpanBuilder {
+    const LEVEL: Option<Level> = Some(Level::INFO);
+    const RECORD_FIELDS: bool = true;
+
+    fn on_request_start(request: &ServiceRequest) -> Span {
+        root_span!(request)
+    }
+
+    fn on_request_end<B>(span: Span, outcome: &Result<ServiceResponse<B>, Error>) {
+        DefaultRootSpanBuilder::on_request_end(span, outcome);
+        if outcome.is_err() {
+            emit_wide_event();
+        }
+    }
+
+    fn on_body_complete(_span: Span, _stats: &BodyStats) {
+        emit_wide_event();
+    }
+}
+
+/// Emit the event of [`WideEventRootSpanBuilder`], from the values recorded on the root span.
+fn emit_wide_event() {
+    let fields = match RecordedFields::current() {
+        Some(fields) => fields.to_json(),
+        None => return,
+    };
+    // Nothing is recorded on disabled spans.
+    if fields.is_empty() {
+        return;
+    }
+    let str = |name: &str| fields.get(name).and_then(JsonValue::as_str);
+    let bool = |name: &str| fields.get(name).and_then(JsonValue::as_bool);
+    let u64 = |name: &str| fields.get(name).and_then(JsonValue::as_u64);
+    let i64 = |name: &str| fields.get(name).and_then(JsonValue::as_i64);
+    let f64 = |name: &str| fields.get(name).and_then(JsonValue::as_f64);
+    // `tracing::info!` doesn't support `#[cfg]` on fields: the fields that can be compiled out
+    // of `root_span!` are prepended according to the enabled features, like `root_span!` does.
+    macro_rules! emit {
+        ($($field:tt)*) => { tracing::info!($($field)* "HTTP request") };
+    }
+    #[cfg(feature = "field-user-agent")]
+    macro_rules! with_user_agent {
+        ($($field:tt)*) => { emit!(http.user_agent = str("http.user_agent"), $($field)*) };
+    }
+    #[cfg(not(feature = "field-user-agent"))]
+    macro_rules! with_user_agent {
+        ($($field:tt)*) => { emit!($($field)*) };
+    }
+    #[cfg(feature = "field-client-ip")]
+    macro_rules! with_client_ip {
+        ($($field:tt)*) => { with_user_agent!(http.client_ip = str("http.client_ip"), $($field)*) };
+    }
+    #[cfg(not(feature = "field-client-ip"))]
+    macro_rules! with_client_ip {
+        ($($field:tt)*) => { with_user_agent!($($field)*) };
+    }
+    #[cfg(feature = "field-host")]
+    macro_rules! with_host {
+        ($($field:tt)*) => { with_client_ip!(http.host = str("http.host"), $($field)*) };
+    }
+    #[cfg(not(feature = "field-host"))]
+    macro_rules! with_host {
+        ($($field:tt)*) => { with_client_ip!($($field)*) };
+    }
+    // Keep it in sync with the fields declared by `root_span!`.
+    with_host!(
+        http.method = str("http.method"),
+        http.method_raw = str("http.method_raw"),
+        http.route = str("http.route"),
+        http.api_version = str("http.api_version"),
+        http.flavor = str("http.flavor"),
+        http.scheme = str("http.scheme"),
+        http.host.allowed = bool("http.host.allowed"),
+        http.target = str("http.target"),
+        http.status_code = i64("http.status_code"),
+        http.response.bytes_written = u64("http.response.bytes_written"),
+        http.response.throughput_bps = u64("http.response.throughput_bps"),
+        http.response.streamed = bool("http.response.streamed"),
+        http.response.etag = str("http.response.etag"),
+        http.response.server_timing = str("http.response.server_timing"),
+        http.outcome = str("http.outcome"),
+        http.server.duration_ms = f64("http.server.duration_ms"),
+        http.server.busy_ms = f64("http.server.busy_ms"),
+        http.deadline_ms = f64("http.deadline_ms"),
+        http.deadline_remaining_ms = f64("http.deadline_remaining_ms"),
+        server.inflight_requests = u64("server.inflight_requests"),
+        net.time_to_first_request_ms = f64("net.time_to_first_request_ms"),
+        net.connection.reused = bool("net.connection.reused"),
+        net.full_duration_ms = f64("net.full_duration_ms"),
+        tls.resumed = bool("tls.resumed"),
+        tls.handshake.duration_ms = f64("tls.handshake.duration_ms"),
+        tls.ja3 = str("tls.ja3"),
+        tls.ja4 = str("tls.ja4"),
+        client.channel = str("client.channel"),
+        http.route.operation_id = str("http.route.operation_id"),
+        http.request.cost = u64("http.request.cost"),
+        http.route.guard.matched = str("http.route.guard.matched"),
+        http.route.guard.rejected = str("http.route.guard.rejected"),
+        http.cors.allowed = bool("http.cors.allowed"),
+        http.cors.origin = str("http.cors.origin"),
+        http.request.range = str("http.request.range"),
+        http.request.transfer_encoding = str("http.request.transfer_encoding"),
+        http.request.max_forwards = i64("http.request.max_forwards"),
+        http.request.authenticated = bool("http.request.authenticated"),
+        http.idempotency.key = str("http.idempotency.key"),
+        http.idempotency.replayed = bool("http.idempotency.replayed"),
+        http.request.conditional = bool("http.request.conditional"),
+        http.request.if_none_match = str("http.request.if_none_match"),
+        http.request.headers = str("http.request.headers"),
+        http.request.query = str("http.request.query"),
+        http.app_data.types = str("http.app_data.types"),
+        http.app_data.missing = str("http.app_data.missing"),
+        otel.kind = str("otel.kind"),
+        otel.status_code = str("otel.status_code"),
+        trace_id = str("trace_id"),
+        request_id = str("request_id"),
+        tracing_actix_web.overhead_us = u64("tracing_actix_web.overhead_us"),
+        tracing_actix_web.verbose = bool("tracing_actix_web.verbose"),
+        http.request.body_bytes_read = u64("http.request.body_bytes_read"),
+        http.request.body_prefix = str("http.request.body_prefix"),
+        http.request.content_length_mismatch = bool("http.request.content_length_mismatch"),
+        http.request.multipart_part_count = u64("http.request.multipart_part_count"),
+        enduser.credential_id = str("enduser.credential_id"),
+        db.transaction = bool("db.transaction"),
+        handler.memoized = bool("handler.memoized"),
+        feature_flags = str("feature_flags"),
+        pending_fields = str("pending_fields"),
+        exception.message = str("exception.message"),
+        validation.extractor = str("validation.extractor"),
+        validation.field = str("validation.field"),
+        exception.details = str("exception.details"),
+    );
+}
+
 fn handle_error(span: Span, status_code: StatusCode, response_error: &dyn ResponseError) {
     // pre-formatting errors is a workaround for https://github.com/tokio-rs/tracing/issues/1565
     let display = format!("{}", response_error);
     let debug = format!("{:?}", response_error);
-    span.record("exception.message", &tracing::field::display(display));
-    span.record("exception.details", &tracing::field::display(debug));
+    record(&span, "exception.message", tracing::field::display(display));
+    record(&span, "exception.details", tracing::field::display(debug));
     let code: i32 = status_code.as_u16().into();
 
-    span.record("http.status_code", &code);
+    record(&span, "http.status_code", code);
 
     if status_code.is_client_error() {
-        span.record("otel.status_code", &"OK");
+        record(&span, "otel.status_code", OtelStatus::Ok.as_str());
     } else {
-        span.record("otel.status_code", &"ERROR");
+        record(&span, "otel.status_code", OtelStatus::Error.as_str());
+    }
+}
+
+fn record_validation_error(span: &Span, error: &Error) {
+    if let Some(validation) = ValidationError::from_error(error) {
+        record(span, "validation.extractor", validation.extractor);
+        if let Some(field) = &validation.field {
+            record(span, "validation.field", field.as_str());
+        }
+    }
+}
+
+fn record_guard_decisions(span: &Span, decisions: &GuardDecisions) {
+    if let Some(matched) = decisions.names(true) {
+        record(
+            span,
+            "http.route.guard.matched",
+            tracing::field::display(matched),
+        );
+    }
+    if let Some(rejected) = decisions.names(false) {
+        record(
+            span,
+            "http.route.guard.rejected",
+            tracing::field::display(rejected),
+        );
+    }
+}
+
+fn record_cors_outcome(span: &Span, cors: &CorsOutcome) {
+    record(span, "http.cors.allowed", cors.allowed());
+    if !cors.allowed() {
+        if let Some(origin) = cors.origin() {
+            record(span, "http.cors.origin", origin);
+        }
+    }
+}
+
+fn record_etag<B>(span: &Span, response: &ServiceResponse<B>) {
+    let settings = RequestSettings::get(response.request());
+    if !settings.capture(settings.settings.record_etag) {
+        return;
+    }
+    if let Some(etag) = response.headers().get(ETAG).and_then(entity_tag) {
+        record(span, "http.response.etag", etag.as_str());
+    }
+}
+
+fn record_server_timing<B>(span: &Span, response: &ServiceResponse<B>) {
+    let settings = RequestSettings::get(response.request());
+    if !settings.capture(settings.settings.record_server_timing) {
+        return;
+    }
+    if let Some(server_timing) = server_timing(response.headers()) {
+        record(span, "http.response.server_timing", server_timing.as_str());
+    }
+}
+
+fn record_app_data_probes(span: &Span, request: &HttpRequest) {
+    let settings = RequestSettings::get(request);
+    if !settings.debug || settings.settings.app_data_probes.is_empty() {
+        return;
+    }
+    let (registered, missing): (Vec<&AppDataProbe>, Vec<&AppDataProbe>) = settings
+        .settings
+        .app_data_probes
+        .iter()
+        .partition(|probe| (probe.is_registered)(request));
+    record(
+        span,
+        "http.app_data.types",
+        type_names(&registered).as_str(),
+    );
+    record(span, "http.app_data.missing", type_names(&missing).as_str());
+}
+
+fn type_names(probes: &[&AppDataProbe]) -> String {
+    probes
+        .iter()
+        .map(|probe| probe.type_name)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn record_operation_id(span: &Span, request: &HttpRequest) {
+    let operation_id = request
+        .app_data::<OperationIds>()
+        .zip(request.match_pattern())
+        .and_then(|(ids, pattern)| ids.get(&pattern).map(ToOwned::to_owned));
+    if let Some(operation_id) = operation_id {
+        record(span, "http.route.operation_id", operation_id.as_str());
     }
 }
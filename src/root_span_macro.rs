@@ -85,54 +85,15 @@ macro_rules! root_span {
 macro_rules! trace_root_span {
     // Vanilla root span, with no additional fields
     ($request:ident) => {
-        trace_root_span!($request, )
+        $crate::trace_root_span!($request,)
     };
     // One or more additional fields, comma separated
     ($request:ident, $($field:tt)*) => {
-        {
-            let user_agent = $request
-                .headers()
-                .get("User-Agent")
-                .map(|h| h.to_str().unwrap_or(""))
-                .unwrap_or("");
-            let http_route: std::borrow::Cow<'static, str> = $request
-                .match_pattern()
-                .map(Into::into)
-                .unwrap_or_else(|| "default".into());
-            let http_method = $crate::root_span_macro::private::http_method_str($request.method());
-            let connection_info = $request.connection_info();
-            let request_id = $crate::root_span_macro::private::get_request_id($request);
-            let span = $crate::root_span_macro::private::tracing::trace_span!(
-                "HTTP request",
-                http.method = %http_method,
-                http.route = %http_route,
-                http.flavor = %$crate::root_span_macro::private::http_flavor($request.version()),
-                http.scheme = %$crate::root_span_macro::private::http_scheme(connection_info.scheme()),
-                http.host = %connection_info.host(),
-                http.client_ip = %$request.connection_info().realip_remote_addr().unwrap_or(""),
-                http.user_agent = %user_agent,
-                http.target = %$request.uri().path_and_query().map(|p| p.as_str()).unwrap_or(""),
-                http.status_code = $crate::root_span_macro::private::tracing::field::Empty,
-                otel.kind = "server",
-                otel.status_code = $crate::root_span_macro::private::tracing::field::Empty,
-                trace_id = $crate::root_span_macro::private::tracing::field::Empty,
-                request_id = %request_id,
-                exception.message = $crate::root_span_macro::private::tracing::field::Empty,
-                // Not proper OpenTelemetry, but their terminology is fairly exception-centric
-                exception.details = $crate::root_span_macro::private::tracing::field::Empty,
-                $($field)*
-            );
-            std::mem::drop(connection_info);
-
-            // Previously, this line was instrumented with an opentelemetry-specific feature
-            // flag check. However, this resulted in the feature flags being resolved in the crate
-            // which called `root_span!` as opposed to being resolved by this crate as expected.
-            // Therefore, this function simply wraps an internal function with the feature flags
-            // to ensure that the flags are resolved against this crate.
-            $crate::root_span_macro::private::set_otel_parent(&$request, &span);
-
-            span
-        }
+        $crate::__private_root_span!(
+            $crate::root_span_macro::private::tracing::Level::TRACE,
+            $request,
+            $($field)*
+        )
     };
 }
 
@@ -143,54 +104,15 @@ macro_rules! trace_root_span {
 macro_rules! debug_root_span {
     // Vanilla root span, with no additional fields
     ($request:ident) => {
-        debug_root_span!($request, )
+        $crate::debug_root_span!($request,)
     };
     // One or more additional fields, comma separated
     ($request:ident, $($field:tt)*) => {
-        {
-            let user_agent = $request
-                .headers()
-                .get("User-Agent")
-                .map(|h| h.to_str().unwrap_or(""))
-                .unwrap_or("");
-            let http_route: std::borrow::Cow<'static, str> = $request
-                .match_pattern()
-                .map(Into::into)
-                .unwrap_or_else(|| "default".into());
-            let http_method = $crate::root_span_macro::private::http_method_str($request.method());
-            let connection_info = $request.connection_info();
-            let request_id = $crate::root_span_macro::private::get_request_id($request);
-            let span = $crate::root_span_macro::private::tracing::debug_span!(
-                "HTTP request",
-                http.method = %http_method,
-                http.route = %http_route,
-                http.flavor = %$crate::root_span_macro::private::http_flavor($request.version()),
-                http.scheme = %$crate::root_span_macro::private::http_scheme(connection_info.scheme()),
-                http.host = %connection_info.host(),
-                http.client_ip = %$request.connection_info().realip_remote_addr().unwrap_or(""),
-                http.user_agent = %user_agent,
-                http.target = %$request.uri().path_and_query().map(|p| p.as_str()).unwrap_or(""),
-                http.status_code = $crate::root_span_macro::private::tracing::field::Empty,
-                otel.kind = "server",
-                otel.status_code = $crate::root_span_macro::private::tracing::field::Empty,
-                trace_id = $crate::root_span_macro::private::tracing::field::Empty,
-                request_id = %request_id,
-                exception.message = $crate::root_span_macro::private::tracing::field::Empty,
-                // Not proper OpenTelemetry, but their terminology is fairly exception-centric
-                exception.details = $crate::root_span_macro::private::tracing::field::Empty,
-                $($field)*
-            );
-            std::mem::drop(connection_info);
-
-            // Previously, this line was instrumented with an opentelemetry-specific feature
-            // flag check. However, this resulted in the feature flags being resolved in the crate
-            // which called `root_span!` as opposed to being resolved by this crate as expected.
-            // Therefore, this function simply wraps an internal function with the feature flags
-            // to ensure that the flags are resolved against this crate.
-            $crate::root_span_macro::private::set_otel_parent(&$request, &span);
-
-            span
-        }
+        $crate::__private_root_span!(
+            $crate::root_span_macro::private::tracing::Level::DEBUG,
+            $request,
+            $($field)*
+        )
     };
 }
 
@@ -201,54 +123,15 @@ macro_rules! debug_root_span {
 macro_rules! info_root_span {
     // Vanilla root span, with no additional fields
     ($request:ident) => {
-       info_root_span!($request,)
+        $crate::info_root_span!($request,)
     };
     // One or more additional fields, comma separated
     ($request:ident, $($field:tt)*) => {
-        {
-            let user_agent = $request
-                .headers()
-                .get("User-Agent")
-                .map(|h| h.to_str().unwrap_or(""))
-                .unwrap_or("");
-            let http_route: std::borrow::Cow<'static, str> = $request
-                .match_pattern()
-                .map(Into::into)
-                .unwrap_or_else(|| "default".into());
-            let http_method = $crate::root_span_macro::private::http_method_str($request.method());
-            let connection_info = $request.connection_info();
-            let request_id = $crate::root_span_macro::private::get_request_id($request);
-            let span = $crate::root_span_macro::private::tracing::info_span!(
-                "HTTP request",
-                http.method = %http_method,
-                http.route = %http_route,
-                http.flavor = %$crate::root_span_macro::private::http_flavor($request.version()),
-                http.scheme = %$crate::root_span_macro::private::http_scheme(connection_info.scheme()),
-                http.host = %connection_info.host(),
-                http.client_ip = %$request.connection_info().realip_remote_addr().unwrap_or(""),
-                http.user_agent = %user_agent,
-                http.target = %$request.uri().path_and_query().map(|p| p.as_str()).unwrap_or(""),
-                http.status_code = $crate::root_span_macro::private::tracing::field::Empty,
-                otel.kind = "server",
-                otel.status_code = $crate::root_span_macro::private::tracing::field::Empty,
-                trace_id = $crate::root_span_macro::private::tracing::field::Empty,
-                request_id = %request_id,
-                exception.message = $crate::root_span_macro::private::tracing::field::Empty,
-                // Not proper OpenTelemetry, but their terminology is fairly exception-centric
-                exception.details = $crate::root_span_macro::private::tracing::field::Empty,
-                $($field)*
-            );
-            std::mem::drop(connection_info);
-
-            // Previously, this line was instrumented with an opentelemetry-specific feature
-            // flag check. However, this resulted in the feature flags being resolved in the crate
-            // which called `root_span!` as opposed to being resolved by this crate as expected.
-            // Therefore, this function simply wraps an internal function with the feature flags
-            // to ensure that the flags are resolved against this crate.
-            $crate::root_span_macro::private::set_otel_parent(&$request, &span);
-
-            span
-        }
+        $crate::__private_root_span!(
+            $crate::root_span_macro::private::tracing::Level::INFO,
+            $request,
+            $($field)*
+        )
     };
 }
 
@@ -259,54 +142,15 @@ macro_rules! info_root_span {
 macro_rules! warn_root_span {
     // Vanilla root span, with no additional fields
     ($request:ident) => {
-        warn_root_span!($request, )
+        $crate::warn_root_span!($request,)
     };
     // One or more additional fields, comma separated
     ($request:ident, $($field:tt)*) => {
-        {
-            let user_agent = $request
-                .headers()
-                .get("User-Agent")
-                .map(|h| h.to_str().unwrap_or(""))
-                .unwrap_or("");
-            let http_route: std::borrow::Cow<'static, str> = $request
-                .match_pattern()
-                .map(Into::into)
-                .unwrap_or_else(|| "default".into());
-            let http_method = $crate::root_span_macro::private::http_method_str($request.method());
-            let connection_info = $request.connection_info();
-            let request_id = $crate::root_span_macro::private::get_request_id($request);
-            let span = $crate::root_span_macro::private::tracing::warn_span!(
-                "HTTP request",
-                http.method = %http_method,
-                http.route = %http_route,
-                http.flavor = %$crate::root_span_macro::private::http_flavor($request.version()),
-                http.scheme = %$crate::root_span_macro::private::http_scheme(connection_info.scheme()),
-                http.host = %connection_info.host(),
-                http.client_ip = %$request.connection_info().realip_remote_addr().unwrap_or(""),
-                http.user_agent = %user_agent,
-                http.target = %$request.uri().path_and_query().map(|p| p.as_str()).unwrap_or(""),
-                http.status_code = $crate::root_span_macro::private::tracing::field::Empty,
-                otel.kind = "server",
-                otel.status_code = $crate::root_span_macro::private::tracing::field::Empty,
-                trace_id = $crate::root_span_macro::private::tracing::field::Empty,
-                request_id = %request_id,
-                exception.message = $crate::root_span_macro::private::tracing::field::Empty,
-                // Not proper OpenTelemetry, but their terminology is fairly exception-centric
-                exception.details = $crate::root_span_macro::private::tracing::field::Empty,
-                $($field)*
-            );
-            std::mem::drop(connection_info);
-
-            // Previously, this line was instrumented with an opentelemetry-specific feature
-            // flag check. However, this resulted in the feature flags being resolved in the crate
-            // which called `root_span!` as opposed to being resolved by this crate as expected.
-            // Therefore, this function simply wraps an internal function with the feature flags
-            // to ensure that the flags are resolved against this crate.
-            $crate::root_span_macro::private::set_otel_parent(&$request, &span);
-
-            span
-        }
+        $crate::__private_root_span!(
+            $crate::root_span_macro::private::tracing::Level::WARN,
+            $request,
+            $($field)*
+        )
     };
 }
 
@@ -317,41 +161,38 @@ macro_rules! warn_root_span {
 macro_rules! error_root_span {
     // Vanilla root span, with no additional fields
     ($request:ident) => {
-        error_root_span!($request, )
+        $crate::error_root_span!($request,)
     };
     // One or more additional fields, comma separated
     ($request:ident, $($field:tt)*) => {
+        $crate::__private_root_span!(
+            $crate::root_span_macro::private::tracing::Level::ERROR,
+            $request,
+            $($field)*
+        )
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+// Shared implementation of all the `*_root_span!` macros.
+// It is not part of the public interface of `tracing-actix-web`.
+//
+// The fields that can be compiled out using cargo features are added by a chain of staging
+// macros (`__private_root_span_field_*!`), each defined twice: the definition to use is chosen
+// when compiling `tracing-actix-web`, therefore it honours the features enabled on this crate
+// rather than on the crate calling `root_span!`.
+// Each stage appends its field (or nothing) to the accumulated fields and calls the next one.
+macro_rules! __private_root_span {
+    ($level:expr, $request:ident, $($field:tt)*) => {
         {
-            let user_agent = $request
-                .headers()
-                .get("User-Agent")
-                .map(|h| h.to_str().unwrap_or(""))
-                .unwrap_or("");
-            let http_route: std::borrow::Cow<'static, str> = $request
-                .match_pattern()
-                .map(Into::into)
-                .unwrap_or_else(|| "default".into());
-            let http_method = $crate::root_span_macro::private::http_method_str($request.method());
+            let http_route = $crate::root_span_macro::private::http_route($request);
+            let http_method = $crate::root_span_macro::private::http_method($request);
             let connection_info = $request.connection_info();
             let request_id = $crate::root_span_macro::private::get_request_id($request);
-            let span = $crate::root_span_macro::private::tracing::error_span!(
-                "HTTP request",
-                http.method = %http_method,
-                http.route = %http_route,
-                http.flavor = %$crate::root_span_macro::private::http_flavor($request.version()),
-                http.scheme = %$crate::root_span_macro::private::http_scheme(connection_info.scheme()),
-                http.host = %connection_info.host(),
-                http.client_ip = %$request.connection_info().realip_remote_addr().unwrap_or(""),
-                http.user_agent = %user_agent,
-                http.target = %$request.uri().path_and_query().map(|p| p.as_str()).unwrap_or(""),
-                http.status_code = $crate::root_span_macro::private::tracing::field::Empty,
-                otel.kind = "server",
-                otel.status_code = $crate::root_span_macro::private::tracing::field::Empty,
-                trace_id = $crate::root_span_macro::private::tracing::field::Empty,
-                request_id = %request_id,
-                exception.message = $crate::root_span_macro::private::tracing::field::Empty,
-                // Not proper OpenTelemetry, but their terminology is fairly exception-centric
-                exception.details = $crate::root_span_macro::private::tracing::field::Empty,
+            let span = $crate::__private_root_span_field_host!(
+                ($level, $request, connection_info, http_method, http_route, request_id)
+                []
                 $($field)*
             );
             std::mem::drop(connection_info);
@@ -362,25 +203,187 @@ macro_rules! error_root_span {
             // Therefore, this function simply wraps an internal function with the feature flags
             // to ensure that the flags are resolved against this crate.
             $crate::root_span_macro::private::set_otel_parent(&$request, &span);
+            $crate::root_span_macro::private::record_pending_fields($request, &span);
 
             span
         }
     };
 }
 
+#[cfg(feature = "field-host")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __private_root_span_field_host {
+    (($level:expr, $request:ident, $connection_info:ident, $($context:tt)*) [$($acc:tt)*] $($field:tt)*) => {
+        $crate::__private_root_span_field_client_ip!(
+            ($level, $request, $connection_info, $($context)*)
+            [$($acc)* http.host = $crate::root_span_macro::private::capture("http.host", $crate::root_span_macro::private::tracing::field::display($connection_info.host())),]
+            $($field)*
+        )
+    };
+}
+
+#[cfg(not(feature = "field-host"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __private_root_span_field_host {
+    (($($context:tt)*) [$($acc:tt)*] $($field:tt)*) => {
+        $crate::__private_root_span_field_client_ip!(($($context)*) [$($acc)*] $($field)*)
+    };
+}
+
+#[cfg(feature = "field-client-ip")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __private_root_span_field_client_ip {
+    (($level:expr, $request:ident, $connection_info:ident, $($context:tt)*) [$($acc:tt)*] $($field:tt)*) => {
+        $crate::__private_root_span_field_user_agent!(
+            ($level, $request, $connection_info, $($context)*)
+            [$($acc)* http.client_ip = $crate::root_span_macro::private::capture("http.client_ip", $crate::root_span_macro::private::tracing::field::display($connection_info.realip_remote_addr().unwrap_or(""))),]
+            $($field)*
+        )
+    };
+}
+
+#[cfg(not(feature = "field-client-ip"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __private_root_span_field_client_ip {
+    (($($context:tt)*) [$($acc:tt)*] $($field:tt)*) => {
+        $crate::__private_root_span_field_user_agent!(($($context)*) [$($acc)*] $($field)*)
+    };
+}
+
+#[cfg(feature = "field-user-agent")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __private_root_span_field_user_agent {
+    (($level:expr, $request:ident, $($context:tt)*) [$($acc:tt)*] $($field:tt)*) => {
+        $crate::__private_root_span_span!(
+            ($level, $request, $($context)*)
+            [$($acc)* http.user_agent = $crate::root_span_macro::private::capture("http.user_agent", $crate::root_span_macro::private::tracing::field::display($crate::root_span_macro::private::user_agent($request))),]
+            $($field)*
+        )
+    };
+}
+
+#[cfg(not(feature = "field-user-agent"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __private_root_span_field_user_agent {
+    (($($context:tt)*) [$($acc:tt)*] $($field:tt)*) => {
+        $crate::__private_root_span_span!(($($context)*) [$($acc)*] $($field)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+// The last stage of `__private_root_span!`: it creates the span.
+// Values go through `capture`, which keeps a copy of them for the requests whose recorded fields
+// are needed once they have been processed (see `RecordedFields`).
+macro_rules! __private_root_span_span {
+    (
+        ($level:expr, $request:ident, $connection_info:ident, $http_method:ident, $http_route:ident, $request_id:ident)
+        [$($optional:tt)*]
+        $($field:tt)*
+    ) => {
+        $crate::root_span_macro::private::tracing::span!(
+            $level,
+            "HTTP request",
+            http.method = $crate::root_span_macro::private::capture("http.method", $crate::root_span_macro::private::tracing::field::display($http_method)),
+            http.method_raw = $crate::root_span_macro::private::capture("http.method_raw", $crate::root_span_macro::private::http_method_raw($request)),
+            http.route = $crate::root_span_macro::private::capture("http.route", $crate::root_span_macro::private::tracing::field::display($http_route)),
+            http.api_version = $crate::root_span_macro::private::capture("http.api_version", $crate::root_span_macro::private::api_version($request)),
+            http.flavor = $crate::root_span_macro::private::capture("http.flavor", $crate::root_span_macro::private::tracing::field::display($crate::root_span_macro::private::http_flavor($request.version()))),
+            http.scheme = $crate::root_span_macro::private::capture("http.scheme", $crate::root_span_macro::private::tracing::field::display($crate::root_span_macro::private::http_scheme($connection_info.scheme()))),
+            $($optional)*
+            http.host.allowed = $crate::root_span_macro::private::capture("http.host.allowed", $crate::root_span_macro::private::host_allowed($request, &$connection_info)),
+            http.target = $crate::root_span_macro::private::capture("http.target", $crate::root_span_macro::private::tracing::field::display($crate::root_span_macro::private::http_target($request))),
+            http.status_code = $crate::root_span_macro::private::tracing::field::Empty,
+            http.response.bytes_written = $crate::root_span_macro::private::tracing::field::Empty,
+            http.response.throughput_bps = $crate::root_span_macro::private::tracing::field::Empty,
+            http.response.streamed = $crate::root_span_macro::private::tracing::field::Empty,
+            http.response.etag = $crate::root_span_macro::private::tracing::field::Empty,
+            http.response.server_timing = $crate::root_span_macro::private::tracing::field::Empty,
+            http.outcome = $crate::root_span_macro::private::tracing::field::Empty,
+            http.server.duration_ms = $crate::root_span_macro::private::tracing::field::Empty,
+            http.server.busy_ms = $crate::root_span_macro::private::tracing::field::Empty,
+            http.deadline_ms = $crate::root_span_macro::private::capture("http.deadline_ms", $crate::root_span_macro::private::deadline_ms($request)),
+            http.deadline_remaining_ms = $crate::root_span_macro::private::tracing::field::Empty,
+            server.inflight_requests = $crate::root_span_macro::private::capture("server.inflight_requests", $crate::root_span_macro::private::inflight_requests($request)),
+            net.time_to_first_request_ms = $crate::root_span_macro::private::capture("net.time_to_first_request_ms", $crate::root_span_macro::private::time_to_first_request_ms($request)),
+            net.connection.reused = $crate::root_span_macro::private::capture("net.connection.reused", $crate::root_span_macro::private::connection_reused($request)),
+            net.full_duration_ms = $crate::root_span_macro::private::tracing::field::Empty,
+            tls.resumed = $crate::root_span_macro::private::capture("tls.resumed", $crate::root_span_macro::private::tls_resumed($request)),
+            tls.handshake.duration_ms = $crate::root_span_macro::private::capture("tls.handshake.duration_ms", $crate::root_span_macro::private::tls_handshake_duration_ms($request)),
+            tls.ja3 = $crate::root_span_macro::private::capture("tls.ja3", $crate::root_span_macro::private::tls_ja3($request)),
+            tls.ja4 = $crate::root_span_macro::private::capture("tls.ja4", $crate::root_span_macro::private::tls_ja4($request)),
+            client.channel = $crate::root_span_macro::private::capture("client.channel", $crate::root_span_macro::private::client_channel($request)),
+            http.route.operation_id = $crate::root_span_macro::private::tracing::field::Empty,
+            http.request.cost = $crate::root_span_macro::private::capture("http.request.cost", $crate::root_span_macro::private::request_cost($request)),
+            http.route.guard.matched = $crate::root_span_macro::private::tracing::field::Empty,
+            http.route.guard.rejected = $crate::root_span_macro::private::tracing::field::Empty,
+            http.cors.allowed = $crate::root_span_macro::private::tracing::field::Empty,
+            http.cors.origin = $crate::root_span_macro::private::tracing::field::Empty,
+            http.request.range = $crate::root_span_macro::private::capture("http.request.range", $crate::root_span_macro::private::range($request)),
+            http.request.transfer_encoding = $crate::root_span_macro::private::capture("http.request.transfer_encoding", $crate::root_span_macro::private::transfer_encoding($request)),
+            http.request.max_forwards = $crate::root_span_macro::private::capture("http.request.max_forwards", $crate::root_span_macro::private::max_forwards($request)),
+            http.request.authenticated = $crate::root_span_macro::private::capture("http.request.authenticated", $crate::root_span_macro::private::authenticated($request)),
+            http.idempotency.key = $crate::root_span_macro::private::capture("http.idempotency.key", $crate::root_span_macro::private::idempotency_key($request)),
+            http.idempotency.replayed = $crate::root_span_macro::private::tracing::field::Empty,
+            http.request.conditional = $crate::root_span_macro::private::capture("http.request.conditional", $crate::root_span_macro::private::conditional($request)),
+            http.request.if_none_match = $crate::root_span_macro::private::capture("http.request.if_none_match", $crate::root_span_macro::private::if_none_match($request)),
+            http.request.headers = $crate::root_span_macro::private::capture("http.request.headers", $crate::root_span_macro::private::debug_headers($request)),
+            http.request.query = $crate::root_span_macro::private::capture("http.request.query", $crate::root_span_macro::private::debug_query($request)),
+            http.app_data.types = $crate::root_span_macro::private::tracing::field::Empty,
+            http.app_data.missing = $crate::root_span_macro::private::tracing::field::Empty,
+            otel.kind = $crate::root_span_macro::private::capture("otel.kind", "server"),
+            otel.status_code = $crate::root_span_macro::private::tracing::field::Empty,
+            trace_id = $crate::root_span_macro::private::tracing::field::Empty,
+            request_id = $crate::root_span_macro::private::capture("request_id", $crate::root_span_macro::private::tracing::field::display($request_id)),
+            tracing_actix_web.overhead_us = $crate::root_span_macro::private::tracing::field::Empty,
+            tracing_actix_web.verbose = $crate::root_span_macro::private::capture("tracing_actix_web.verbose", $crate::root_span_macro::private::verbose($request)),
+            http.request.body_bytes_read = $crate::root_span_macro::private::tracing::field::Empty,
+            http.request.body_prefix = $crate::root_span_macro::private::tracing::field::Empty,
+            http.request.content_length_mismatch = $crate::root_span_macro::private::tracing::field::Empty,
+            http.request.multipart_part_count = $crate::root_span_macro::private::tracing::field::Empty,
+            enduser.credential_id = $crate::root_span_macro::private::tracing::field::Empty,
+            db.transaction = $crate::root_span_macro::private::tracing::field::Empty,
+            handler.memoized = $crate::root_span_macro::private::tracing::field::Empty,
+            feature_flags = $crate::root_span_macro::private::tracing::field::Empty,
+            pending_fields = $crate::root_span_macro::private::tracing::field::Empty,
+            exception.message = $crate::root_span_macro::private::tracing::field::Empty,
+            validation.extractor = $crate::root_span_macro::private::tracing::field::Empty,
+            validation.field = $crate::root_span_macro::private::tracing::field::Empty,
+            // Not proper OpenTelemetry, but their terminology is fairly exception-centric
+            exception.details = $crate::root_span_macro::private::tracing::field::Empty,
+            $($field)*
+        )
+    };
+}
+
 #[doc(hidden)]
 pub mod private {
     //! This module exposes and re-exports various functions and traits as public in order to leverage them
     //! in the code generated by the `root_span` macro.
     //! Items in this module are not part of the public interface of `tracing-actix-web` - they are considered
     //! implementation details and will change without notice in patch, minor and major releases.
-    use crate::RequestId;
-    use actix_web::dev::ServiceRequest;
+    use crate::settings::{RequestSettings, Settings, DEBUG_HEADER};
+    use crate::{RequestId, TlsConnectionInfo};
+    use actix_web::dev::{ConnectionInfo, ServiceRequest};
+    use actix_web::http::header::{
+        HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, COOKIE, IF_MODIFIED_SINCE,
+        IF_NONE_MATCH, MAX_FORWARDS, PROXY_AUTHORIZATION, RANGE, TRANSFER_ENCODING,
+    };
     use actix_web::http::{Method, Version};
+    use actix_web::HttpMessage;
     use std::borrow::Cow;
+    use std::time::Duration;
 
     pub use tracing;
 
+    const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
     #[doc(hidden)]
     // We need to allow unused variables because the function
     // body is empty if the user of the library chose not to activate
@@ -396,6 +399,344 @@ pub mod private {
         crate::otel::set_otel_parent(req, span);
     }
 
+    #[doc(hidden)]
+    #[inline]
+    pub fn capture<V: tracing::Value>(name: &str, value: V) -> V {
+        crate::fields::capture(name, value)
+    }
+
+    #[doc(hidden)]
+    pub fn record_pending_fields(request: &ServiceRequest, span: &tracing::Span) {
+        crate::PendingSpanFields::drain(request, span);
+    }
+
+    #[doc(hidden)]
+    pub fn http_route(request: &ServiceRequest) -> Cow<'static, str> {
+        match request.match_pattern() {
+            Some(pattern) => pattern.into(),
+            None => RequestSettings::get(request)
+                .settings
+                .unmatched_route
+                .route(request.path())
+                .into(),
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn user_agent(request: &ServiceRequest) -> &str {
+        request
+            .headers()
+            .get("User-Agent")
+            .map(|h| h.to_str().unwrap_or(""))
+            .unwrap_or("")
+    }
+
+    #[doc(hidden)]
+    pub fn api_version(request: &ServiceRequest) -> Option<String> {
+        RequestSettings::get(request)
+            .settings
+            .api_version(request.path(), request.headers())
+    }
+
+    #[doc(hidden)]
+    pub fn deadline_ms(request: &ServiceRequest) -> Option<f64> {
+        RequestSettings::get(request).deadline.map(duration_ms)
+    }
+
+    #[doc(hidden)]
+    pub fn verbose(request: &ServiceRequest) -> Option<bool> {
+        Some(true).filter(|_| RequestSettings::get(request).verbose)
+    }
+
+    #[doc(hidden)]
+    pub fn client_channel(request: &ServiceRequest) -> Option<&'static str> {
+        RequestSettings::get(request).channel
+    }
+
+    #[doc(hidden)]
+    pub fn inflight_requests(request: &ServiceRequest) -> Option<u64> {
+        RequestSettings::get(request)
+            .inflight_requests
+            .map(|count| count as u64)
+    }
+
+    #[doc(hidden)]
+    pub fn request_cost(request: &ServiceRequest) -> Option<u64> {
+        RequestSettings::get(request)
+            .settings
+            .route_cost(request.match_pattern().as_deref())
+    }
+
+    #[doc(hidden)]
+    pub fn time_to_first_request_ms(request: &ServiceRequest) -> Option<f64> {
+        RequestSettings::get(request)
+            .time_to_first_request
+            .map(duration_ms)
+    }
+
+    #[doc(hidden)]
+    pub fn connection_reused(request: &ServiceRequest) -> Option<bool> {
+        RequestSettings::get(request).connection_reused
+    }
+
+    #[doc(hidden)]
+    pub fn tls_resumed(request: &ServiceRequest) -> Option<bool> {
+        request.conn_data::<TlsConnectionInfo>()?.is_resumed()
+    }
+
+    #[doc(hidden)]
+    pub fn tls_handshake_duration_ms(request: &ServiceRequest) -> Option<f64> {
+        let duration = request
+            .conn_data::<TlsConnectionInfo>()?
+            .handshake_duration()?;
+        Some(duration_ms(duration))
+    }
+
+    #[doc(hidden)]
+    pub fn range<R: HttpMessage>(request: &R) -> Option<String> {
+        let settings = RequestSettings::get(request);
+        if !settings.capture(settings.settings.record_range) {
+            return None;
+        }
+        header_value(request, &RANGE, 256)
+    }
+
+    #[doc(hidden)]
+    pub fn host_allowed<R: HttpMessage>(
+        request: &R,
+        connection_info: &ConnectionInfo,
+    ) -> Option<bool> {
+        let settings = RequestSettings::get(request);
+        // Configuring the allowed hosts is the toggle - it doesn't override sensitive routes.
+        if !settings.capture(true) {
+            return None;
+        }
+        settings.settings.is_allowed_host(connection_info.host())
+    }
+
+    #[doc(hidden)]
+    pub fn tls_ja3<R: HttpMessage>(request: &R) -> Option<String> {
+        let settings = RequestSettings::get(request);
+        fingerprint(request, &settings, settings.settings.ja3_header.as_ref()?)
+    }
+
+    #[doc(hidden)]
+    pub fn tls_ja4<R: HttpMessage>(request: &R) -> Option<String> {
+        let settings = RequestSettings::get(request);
+        fingerprint(request, &settings, settings.settings.ja4_header.as_ref()?)
+    }
+
+    /// The TLS fingerprint injected by a fronting proxy in the `header` request header.
+    fn fingerprint<R: HttpMessage>(
+        request: &R,
+        settings: &RequestSettings,
+        header: &HeaderName,
+    ) -> Option<String> {
+        // Configuring the header is the toggle - it doesn't override sensitive routes.
+        if !settings.capture(true) {
+            return None;
+        }
+        header_value(request, header, 256)
+    }
+
+    #[doc(hidden)]
+    pub fn transfer_encoding<R: HttpMessage>(request: &R) -> Option<String> {
+        let settings = RequestSettings::get(request);
+        if !settings.capture(settings.settings.record_transfer_encoding) {
+            return None;
+        }
+        header_value(request, &TRANSFER_ENCODING, 128)
+    }
+
+    #[doc(hidden)]
+    pub fn max_forwards<R: HttpMessage>(request: &R) -> Option<i64> {
+        let settings = RequestSettings::get(request);
+        if !settings.capture(settings.settings.record_max_forwards) {
+            return None;
+        }
+        let value = request.headers().get(MAX_FORWARDS)?.to_str().ok()?.trim();
+        if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        value.parse().ok()
+    }
+
+    #[doc(hidden)]
+    pub fn authenticated<R: HttpMessage>(request: &R) -> Option<bool> {
+        let settings = RequestSettings::get(request);
+        if !settings.capture(settings.settings.record_authenticated) {
+            return None;
+        }
+        Some(request.headers().contains_key(AUTHORIZATION))
+    }
+
+    #[doc(hidden)]
+    pub fn idempotency_key<R: HttpMessage>(request: &R) -> Option<String> {
+        let settings = RequestSettings::get(request);
+        if !settings.capture(settings.settings.record_idempotency_key) {
+            return None;
+        }
+        header_value(request, &IDEMPOTENCY_KEY, 256)
+    }
+
+    #[doc(hidden)]
+    pub fn conditional<R: HttpMessage>(request: &R) -> Option<bool> {
+        let settings = RequestSettings::get(request);
+        if !settings.capture(settings.settings.record_conditional) {
+            return None;
+        }
+        let headers = request.headers();
+        Some(headers.contains_key(IF_NONE_MATCH) || headers.contains_key(IF_MODIFIED_SINCE))
+    }
+
+    #[doc(hidden)]
+    pub fn if_none_match<R: HttpMessage>(request: &R) -> Option<String> {
+        let settings = RequestSettings::get(request);
+        if !settings.capture(settings.settings.record_conditional) {
+            return None;
+        }
+        entity_tag(request.headers().get(IF_NONE_MATCH)?)
+    }
+
+    /// The value of an entity tag header, unless it is too long to be recorded.
+    pub(crate) fn entity_tag(value: &HeaderValue) -> Option<String> {
+        // A truncated entity tag would be misleading: we skip it altogether.
+        if value.len() > 128 {
+            return None;
+        }
+        Some(String::from_utf8_lossy(value.as_bytes()).into_owned())
+    }
+
+    /// The `Server-Timing` header(s) of a response, joined and truncated to 512 bytes.
+    pub(crate) fn server_timing(headers: &HeaderMap) -> Option<String> {
+        let server_timing = HeaderName::from_static("server-timing");
+        let mut values = headers.get_all(server_timing).peekable();
+        values.peek()?;
+        let joined = values
+            .map(|value| String::from_utf8_lossy(value.as_bytes()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        Some(truncate(&joined, 512).to_owned())
+    }
+
+    /// The value of the `name` header, truncated to `max_len` bytes.
+    fn header_value<R: HttpMessage>(
+        request: &R,
+        name: &HeaderName,
+        max_len: usize,
+    ) -> Option<String> {
+        let value = request.headers().get(name)?;
+        let value = String::from_utf8_lossy(value.as_bytes());
+        Some(truncate(&value, max_len).to_string())
+    }
+
+    /// Truncate `value` to, at most, `max_len` bytes, without splitting a character.
+    pub(crate) fn truncate(value: &str, max_len: usize) -> &str {
+        if value.len() <= max_len {
+            return value;
+        }
+        let mut end = max_len;
+        while !value.is_char_boundary(end) {
+            end -= 1;
+        }
+        &value[..end]
+    }
+
+    #[doc(hidden)]
+    pub fn debug_headers<R: HttpMessage>(request: &R) -> Option<String> {
+        if !RequestSettings::get(request).debug {
+            return None;
+        }
+        let mut headers = serde_json::Map::new();
+        for name in request.headers().keys() {
+            let value = if is_redacted(name) {
+                "[redacted]".to_string()
+            } else {
+                request
+                    .headers()
+                    .get_all(name)
+                    .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            headers.insert(name.as_str().to_string(), value.into());
+        }
+        Some(serde_json::Value::Object(headers).to_string())
+    }
+
+    fn is_redacted(name: &HeaderName) -> bool {
+        name == AUTHORIZATION
+            || name == PROXY_AUTHORIZATION
+            || name == COOKIE
+            || name == DEBUG_HEADER
+    }
+
+    #[doc(hidden)]
+    pub fn http_target(request: &ServiceRequest) -> &str {
+        // The query string of sensitive routes might carry credentials (e.g. `?token=..`).
+        if RequestSettings::get(request).sensitive {
+            return request.uri().path();
+        }
+        request
+            .uri()
+            .path_and_query()
+            .map(|p| p.as_str())
+            .unwrap_or("")
+    }
+
+    #[doc(hidden)]
+    pub fn debug_query(request: &ServiceRequest) -> Option<&str> {
+        if !RequestSettings::get(request).debug {
+            return None;
+        }
+        request.uri().query()
+    }
+
+    #[doc(hidden)]
+    pub fn http_method(request: &ServiceRequest) -> Cow<'static, str> {
+        normalized_http_method(request.method(), &RequestSettings::get(request).settings)
+    }
+
+    #[doc(hidden)]
+    pub fn http_method_raw(request: &ServiceRequest) -> Option<String> {
+        raw_http_method(request.method(), &RequestSettings::get(request).settings)
+    }
+
+    /// The value of `http.method`: methods outside of the standard set are recorded as `_OTHER`,
+    /// if normalization is enabled.
+    pub(crate) fn normalized_http_method(
+        method: &Method,
+        settings: &Settings,
+    ) -> Cow<'static, str> {
+        if settings.normalize_http_method && !is_known_method(method) {
+            return "_OTHER".into();
+        }
+        http_method_str(method)
+    }
+
+    /// The value of `http.method_raw`: only recorded if `http.method` has been normalized to `_OTHER`.
+    pub(crate) fn raw_http_method(method: &Method, settings: &Settings) -> Option<String> {
+        if settings.normalize_http_method && !is_known_method(method) {
+            return Some(method.to_string());
+        }
+        None
+    }
+
+    fn is_known_method(method: &Method) -> bool {
+        matches!(
+            *method,
+            Method::OPTIONS
+                | Method::GET
+                | Method::POST
+                | Method::PUT
+                | Method::DELETE
+                | Method::HEAD
+                | Method::TRACE
+                | Method::CONNECT
+                | Method::PATCH
+        )
+    }
+
     #[doc(hidden)]
     #[inline]
     pub fn http_method_str(method: &Method) -> Cow<'static, str> {
@@ -436,6 +777,12 @@ pub mod private {
         }
     }
 
+    #[doc(hidden)]
+    #[inline]
+    pub fn duration_ms(duration: Duration) -> f64 {
+        duration.as_secs_f64() * 1000.0
+    }
+
     #[doc(hidden)]
     pub fn generate_request_id() -> RequestId {
         RequestId::generate()
@@ -443,8 +790,6 @@ pub mod private {
 
     #[doc(hidden)]
     pub fn get_request_id(request: &ServiceRequest) -> RequestId {
-        use actix_web::HttpMessage;
-
         request.extensions().get::<RequestId>().cloned().unwrap()
     }
 }
@@ -0,0 +1,369 @@
+use crate::fields::RecordedFields;
+use crate::{ApiVersionSource, LevelMap, UnmatchedRoutePolicy};
+use actix_web::dev::ServiceRequest;
+use actix_web::http::header::{HeaderMap, HeaderName, CONTENT_LENGTH};
+use actix_web::http::StatusCode;
+use actix_web::{HttpMessage, HttpRequest};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+
+/// The header clients must set to the configured debug secret to flag a request for debugging.
+pub(crate) const DEBUG_HEADER: HeaderName = HeaderName::from_static("x-trace-debug");
+
+const GRPC_TIMEOUT: HeaderName = HeaderName::from_static("grpc-timeout");
+const REQUEST_TIMEOUT: HeaderName = HeaderName::from_static("x-request-timeout");
+
+/// The configuration of a [`TracingLogger`] instance.
+///
+/// [`TracingLogger`]: crate::TracingLogger
+#[derive(Clone, Default)]
+pub(crate) struct Settings {
+    pub(crate) unmatched_route: UnmatchedRoutePolicy,
+    pub(crate) normalize_http_method: bool,
+    pub(crate) api_version_source: Option<ApiVersionSource>,
+    pub(crate) default_api_version: Option<String>,
+    pub(crate) debug_secret: Option<String>,
+    pub(crate) verbose_clients: Option<VerboseClients>,
+    pub(crate) body_size_threshold: Option<u64>,
+    pub(crate) skip_unknown_body_size: bool,
+    pub(crate) lazy_root_span: bool,
+    pub(crate) app_data_probes: Vec<AppDataProbe>,
+    pub(crate) record_duration: bool,
+    pub(crate) record_busy_time: bool,
+    pub(crate) record_range: bool,
+    pub(crate) ja3_header: Option<HeaderName>,
+    pub(crate) ja4_header: Option<HeaderName>,
+    pub(crate) record_transfer_encoding: bool,
+    pub(crate) record_max_forwards: bool,
+    pub(crate) record_conditional: bool,
+    pub(crate) record_etag: bool,
+    pub(crate) record_server_timing: bool,
+    pub(crate) record_authenticated: bool,
+    pub(crate) record_idempotency_key: bool,
+    pub(crate) record_content_length_mismatch: bool,
+    pub(crate) record_bytes_written: bool,
+    pub(crate) record_throughput: bool,
+    pub(crate) record_full_duration: bool,
+    pub(crate) record_outcome: bool,
+    pub(crate) record_deadline: bool,
+    pub(crate) record_overhead: bool,
+    pub(crate) route_costs: HashMap<String, u64>,
+    pub(crate) default_route_cost: Option<u64>,
+    pub(crate) sensitive_routes: HashSet<String>,
+    pub(crate) allowed_hosts: Vec<String>,
+    pub(crate) on_error: Option<ErrorCallback>,
+    pub(crate) access_log: Option<AccessLog>,
+    pub(crate) channel_classifier: Option<Arc<ChannelClassifierFn>>,
+    pub(crate) emit_completion_event: bool,
+    pub(crate) completion_event_levels: LevelMap,
+    pub(crate) request_id_prefix: Option<Arc<str>>,
+    /// The number of requests being processed, shared by all the clones of a `TracingLogger`.
+    pub(crate) inflight_requests: Option<Arc<AtomicUsize>>,
+}
+
+pub(crate) type ErrorCallbackFn = dyn Fn(StatusCode, &str) + Send + Sync;
+
+/// The sink JSON access-log lines are written to, shared by all the clones of a `TracingLogger`.
+pub(crate) type AccessLog = Arc<Mutex<Box<dyn Write + Send>>>;
+
+pub(crate) type ChannelClassifierFn = dyn Fn(&ServiceRequest) -> Option<&'static str> + Send + Sync;
+
+/// A callback invoked for the errors whose status code is greater than or equal to `threshold`.
+#[derive(Clone)]
+pub(crate) struct ErrorCallback {
+    pub(crate) threshold: StatusCode,
+    pub(crate) callback: Arc<ErrorCallbackFn>,
+}
+
+/// The clients whose requests should be traced verbosely, identified by the value of `header`.
+#[derive(Clone)]
+pub(crate) struct VerboseClients {
+    pub(crate) clients: Arc<RwLock<HashSet<String>>>,
+    pub(crate) header: HeaderName,
+}
+
+/// Checks if a value of a certain type is available as application data.
+#[derive(Clone, Copy)]
+pub(crate) struct AppDataProbe {
+    pub(crate) type_name: &'static str,
+    pub(crate) is_registered: fn(&HttpRequest) -> bool,
+}
+
+impl AppDataProbe {
+    pub(crate) fn new<T: 'static>() -> Self {
+        fn is_registered<T: 'static>(request: &HttpRequest) -> bool {
+            request.app_data::<T>().is_some()
+        }
+
+        Self {
+            type_name: std::any::type_name::<T>(),
+            is_registered: is_registered::<T>,
+        }
+    }
+}
+
+impl Settings {
+    /// `false` if no root span should be created for `request`.
+    pub(crate) fn should_trace(&self, request: &ServiceRequest) -> bool {
+        let threshold = match self.body_size_threshold {
+            Some(threshold) => threshold,
+            None => return true,
+        };
+        let content_length = request
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok());
+        match content_length {
+            Some(content_length) => content_length > threshold,
+            None => !self.skip_unknown_body_size,
+        }
+    }
+
+    /// The cost of the requests matching `route`, if one has been configured.
+    pub(crate) fn route_cost(&self, route: Option<&str>) -> Option<u64> {
+        route
+            .and_then(|route| self.route_costs.get(route).copied())
+            .or(self.default_route_cost)
+    }
+
+    /// `true` if `request` comes from one of the clients that should be traced verbosely.
+    pub(crate) fn is_verbose_request(&self, request: &ServiceRequest) -> bool {
+        let verbose_clients = match &self.verbose_clients {
+            Some(verbose_clients) => verbose_clients,
+            None => return false,
+        };
+        let client_id = match request
+            .headers()
+            .get(&verbose_clients.header)
+            .and_then(|value| value.to_str().ok())
+        {
+            Some(client_id) => client_id,
+            None => return false,
+        };
+        verbose_clients
+            .clients
+            .read()
+            // The set is still usable if a writer panicked.
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains(client_id)
+    }
+
+    /// The channel the request comes from, according to the configured classifier.
+    pub(crate) fn channel(&self, request: &ServiceRequest) -> Option<&'static str> {
+        (self.channel_classifier.as_ref()?)(request)
+    }
+
+    /// The version of the API targeted by a request, if it is being recorded.
+    pub(crate) fn api_version(&self, path: &str, headers: &HeaderMap) -> Option<String> {
+        self.api_version_source
+            .as_ref()?
+            .version(path, headers)
+            .or_else(|| self.default_api_version.clone())
+    }
+
+    /// Whether `host` (the effective host of a request, possibly followed by a port) is in the
+    /// list of allowed hosts, if one has been configured.
+    pub(crate) fn is_allowed_host(&self, host: &str) -> Option<bool> {
+        if self.allowed_hosts.is_empty() {
+            return None;
+        }
+        let host = strip_port(host).trim_end_matches('.').to_ascii_lowercase();
+        let allowed = self.allowed_hosts.iter().any(|allowed| {
+            match allowed.strip_prefix("*.") {
+                // `*.example.com` matches `api.example.com` and `a.b.example.com`, not `example.com`.
+                Some(domain) => matches!(
+                    host.strip_suffix(domain),
+                    Some(subdomain) if subdomain.len() > 1 && subdomain.ends_with('.')
+                ),
+                None => host == *allowed,
+            }
+        });
+        Some(allowed)
+    }
+
+    /// `true` if `request` matches a route that has been marked as sensitive.
+    pub(crate) fn is_sensitive(&self, request: &ServiceRequest) -> bool {
+        if self.sensitive_routes.is_empty() {
+            return false;
+        }
+        match request.match_pattern() {
+            Some(pattern) => self.sensitive_routes.contains(&pattern),
+            None => false,
+        }
+    }
+
+    /// The deadline advertised by the client using either the `grpc-timeout` or the
+    /// `X-Request-Timeout` header.
+    pub(crate) fn deadline(&self, request: &ServiceRequest) -> Option<Duration> {
+        let header = |name| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        header(GRPC_TIMEOUT)
+            .and_then(parse_grpc_timeout)
+            .or_else(|| header(REQUEST_TIMEOUT).and_then(parse_request_timeout))
+    }
+
+    /// `true` if `request` carries the debug header, set to the configured debug secret.
+    pub(crate) fn is_debug_request(&self, request: &ServiceRequest) -> bool {
+        let secret = match &self.debug_secret {
+            Some(secret) => secret,
+            None => return false,
+        };
+        match request.headers().get(DEBUG_HEADER) {
+            Some(value) => constant_time_eq(value.as_bytes(), secret.as_bytes()),
+            None => false,
+        }
+    }
+}
+
+/// The settings that apply to the in-flight request.
+///
+/// They are stored in request-local storage when a request comes in, so that root span builders
+/// (and the code generated by `root_span!`) can honour them. They are shared, not copied: each
+/// lookup hands out a new reference.
+pub(crate) struct RequestSettings {
+    pub(crate) settings: Rc<Settings>,
+    /// The request has been flagged for debugging using the debug header.
+    pub(crate) debug: bool,
+    /// The request comes from a client that should be traced verbosely.
+    pub(crate) verbose: bool,
+    /// The request matches a sensitive route: no optional field is captured.
+    pub(crate) sensitive: bool,
+    /// When [`TracingLogger`](crate::TracingLogger) started processing the request. It is reset
+    /// when the root span has been created.
+    pub(crate) started_at: Cell<Instant>,
+    /// The deadline advertised by the client, relative to `started_at`, if it is being recorded.
+    pub(crate) deadline: Option<Duration>,
+    /// The number of requests being processed when the request came in, this one included.
+    pub(crate) inflight_requests: Option<usize>,
+    /// The channel the request comes from, if classified.
+    pub(crate) channel: Option<&'static str>,
+    /// When the connection was accepted (or our best estimate), if the request is the first one
+    /// served over it and `net.full_duration_ms` is being recorded.
+    pub(crate) connection_accepted_at: Option<Instant>,
+    /// Whether the connection had already served other requests, if connection metadata is
+    /// available. It is captured before the request is counted against its connection.
+    pub(crate) connection_reused: Option<bool>,
+    /// How long the connection had been established when the request came in, if it is the
+    /// first one served over it.
+    pub(crate) time_to_first_request: Option<Duration>,
+    /// A copy of the values recorded on the root span, if they are needed once the request has
+    /// been processed.
+    pub(crate) recorded_fields: Option<RecordedFields>,
+}
+
+impl Default for RequestSettings {
+    fn default() -> Self {
+        Self {
+            settings: Default::default(),
+            debug: false,
+            verbose: false,
+            sensitive: false,
+            started_at: Cell::new(Instant::now()),
+            deadline: None,
+            inflight_requests: None,
+            channel: None,
+            connection_accepted_at: None,
+            connection_reused: None,
+            time_to_first_request: None,
+            recorded_fields: None,
+        }
+    }
+}
+
+impl RequestSettings {
+    /// Retrieve the settings that apply to `request`.
+    pub(crate) fn get<R: HttpMessage>(request: &R) -> Rc<RequestSettings> {
+        request
+            .extensions()
+            .get::<Rc<RequestSettings>>()
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Whether an optional field should be captured, given the value of its toggle.
+    ///
+    /// Requests flagged for debugging capture all optional fields, requests matching a
+    /// sensitive route capture none.
+    pub(crate) fn capture(&self, enabled: bool) -> bool {
+        !self.sensitive && (enabled || self.debug)
+    }
+}
+
+/// Strip the port, if any, from a `host[:port]` authority - IPv6 addresses are bracketed.
+fn strip_port(host: &str) -> &str {
+    if host.starts_with('[') {
+        return match host.find(']') {
+            Some(end) => &host[..=end],
+            None => host,
+        };
+    }
+    match host.rsplit_once(':') {
+        Some((host, _)) => host,
+        None => host,
+    }
+}
+
+/// Parse a `grpc-timeout` header value - up to 8 digits followed by a unit (e.g. `100m`).
+fn parse_grpc_timeout(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if value.len() < 2 || value.len() > 9 {
+        return None;
+    }
+    let (amount, unit) = value.split_at(value.len() - 1);
+    if !amount.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let amount: u64 = amount.parse().ok()?;
+    match unit {
+        "H" => Some(Duration::from_secs(amount * 60 * 60)),
+        "M" => Some(Duration::from_secs(amount * 60)),
+        "S" => Some(Duration::from_secs(amount)),
+        "m" => Some(Duration::from_millis(amount)),
+        "u" => Some(Duration::from_micros(amount)),
+        "n" => Some(Duration::from_nanos(amount)),
+        _ => None,
+    }
+}
+
+/// Parse a `X-Request-Timeout` header value - a number of milliseconds.
+fn parse_request_timeout(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok().map(Duration::from_millis)
+}
+
+/// Keeps track of a request in the count of the requests being processed, until it is dropped.
+pub(crate) struct InflightGuard(Arc<AtomicUsize>);
+
+impl InflightGuard {
+    /// Add a request to `counter`, returning the guard and the updated count.
+    pub(crate) fn new(counter: &Arc<AtomicUsize>) -> (Self, usize) {
+        let count = counter.fetch_add(1, Ordering::Relaxed) + 1;
+        (Self(counter.clone()), count)
+    }
+}
+
+impl Drop for InflightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,95 @@
+/// How [`TracingLogger`] populates `http.route` when the request did not match any of the routes
+/// registered in your application.
+///
+/// The full path of an unmatched request is attacker-controlled - using it as `http.route` can cause a
+/// cardinality explosion in your telemetry backend. On the other hand, collapsing all unmatched routes
+/// into a single value throws away information that might help you understand what is hitting your
+/// 404s.
+///
+/// `UnmatchedRoutePolicy` lets you choose the right trade-off for your application:
+///
+/// ```rust
+/// use tracing_actix_web::{TracingLogger, UnmatchedRoutePolicy};
+///
+/// // `/users/42/orders` becomes `/users`
+/// let logger = TracingLogger::default().unmatched_route_policy(UnmatchedRoutePolicy::FirstSegments(1));
+/// ```
+///
+/// [`TracingLogger`]: crate::TracingLogger
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum UnmatchedRoutePolicy {
+    /// Record the full path, without the query string (e.g. `/users/42/orders`).
+    ///
+    /// Cardinality is unbounded.
+    FullPath,
+    /// Record only the first `n` segments of the path (e.g. `/users` for `/users/42/orders` if `n` is `1`).
+    FirstSegments(usize),
+    /// Hash the path into one of `n` buckets and record the bucket as `default:<bucket>`
+    /// (e.g. `default:17`).
+    ///
+    /// Cardinality is bounded by `n`. `0` behaves like `1`.
+    ///
+    /// Paths are hashed using 64-bit FNV-1a: a path is assigned to the same bucket across
+    /// processes, releases and Rust versions, so buckets can be compared over time.
+    Hash(u64),
+    /// Record `default` for all unmatched requests.
+    ///
+    /// This is the default policy.
+    #[default]
+    Sentinel,
+}
+
+impl UnmatchedRoutePolicy {
+    pub(crate) fn route(&self, path: &str) -> String {
+        match self {
+            UnmatchedRoutePolicy::FullPath => path.to_string(),
+            UnmatchedRoutePolicy::FirstSegments(n) => {
+                let mut route = String::new();
+                for segment in path.split('/').filter(|s| !s.is_empty()).take(*n) {
+                    route.push('/');
+                    route.push_str(segment);
+                }
+                if route.is_empty() {
+                    route.push('/');
+                }
+                route
+            }
+            UnmatchedRoutePolicy::Hash(buckets) => {
+                format!("default:{}", fnv1a(path.as_bytes()) % buckets.max(&1))
+            }
+            UnmatchedRoutePolicy::Sentinel => "default".to_string(),
+        }
+    }
+}
+
+/// 64-bit FNV-1a: unlike `std`'s `DefaultHasher`, its output is specified and stable.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv1a_matches_the_reference_test_vectors() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn hash_buckets_are_stable() {
+        let policy = UnmatchedRoutePolicy::Hash(32);
+        // Pinned: changing it moves existing paths to different buckets.
+        assert_eq!(policy.route("/users/42/orders"), "default:23");
+        assert_eq!(
+            UnmatchedRoutePolicy::Hash(0).route("/wp-login.php"),
+            "default:0"
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use actix_web::error::{
+    InternalError, JsonPayloadError, PathError, QueryPayloadError, UrlencodedError,
+};
+use actix_web::{Error, ResponseError};
+use std::fmt::Display;
+
+/// A request rejected by one of the extractors provided by `actix-web`.
+pub(crate) struct ValidationError {
+    /// The extractor that rejected the request (e.g. `Json`).
+    pub(crate) extractor: &'static str,
+    /// The field that failed validation, if it can be determined.
+    pub(crate) field: Option<String>,
+}
+
+impl ValidationError {
+    /// Recognise the errors returned by the `Json`, `Path`, `Query` and `Form` extractors, as
+    /// they come out of `actix-web` or wrapped in an [`InternalError`] by a custom error handler.
+    pub(crate) fn from_error(error: &Error) -> Option<Self> {
+        fn downcast<T: ResponseError + Display + 'static>(error: &Error) -> Option<String> {
+            let message = match error.as_error::<T>() {
+                Some(error) => error.to_string(),
+                None => error.as_error::<InternalError<T>>()?.to_string(),
+            };
+            Some(message)
+        }
+
+        let (extractor, message) = if let Some(message) = downcast::<JsonPayloadError>(error) {
+            ("Json", message)
+        } else if let Some(message) = downcast::<PathError>(error) {
+            ("Path", message)
+        } else if let Some(message) = downcast::<QueryPayloadError>(error) {
+            ("Query", message)
+        } else if let Some(message) = downcast::<UrlencodedError>(error) {
+            ("Form", message)
+        } else if let Some(error) = error.as_error::<InternalError<serde::de::value::Error>>() {
+            // The default error handler of the `Path` extractor wraps the deserialization error.
+            ("Path", error.to_string())
+        } else {
+            return None;
+        };
+        Some(Self {
+            extractor,
+            field: field_name(&message),
+        })
+    }
+}
+
+/// Extract the name of the offending field from a `serde` deserialization error message
+/// (e.g. ``missing field `name` at line 1 column 2``).
+fn field_name(message: &str) -> Option<String> {
+    ["missing field `", "unknown field `", "duplicate field `"]
+        .iter()
+        .find_map(|prefix| {
+            let start = message.find(prefix)? + prefix.len();
+            let length = message[start..].find('`')?;
+            Some(message[start..start + length].to_owned())
+        })
+}
@@ -0,0 +1,124 @@
+use crate::RequestId;
+use actix_web::body::BoxBody;
+use actix_web::http::header::{HeaderValue, CONTENT_TYPE};
+use actix_web::http::StatusCode;
+use actix_web::{Error, HttpResponse, ResponseError};
+
+/// Wrap `error` to include the [request id](RequestId) of the in-flight request in the body
+/// of the error response.
+///
+/// The request id is captured when `with_request_id` is called: it is the same value you would
+/// get using the [`RequestId`] extractor.
+/// If `with_request_id` is called outside of the processing of a request by [`TracingLogger`]
+/// (e.g. in a task spawned from your handler) there is no request id to capture - use
+/// [`WithRequestId::new`] with the value returned by the [`RequestId`] extractor instead.
+///
+/// Check out [`WithRequestId`] for the format of the error response.
+///
+/// # Usage
+///
+/// ```rust
+/// use actix_web::error::ErrorNotFound;
+/// use actix_web::HttpResponse;
+/// use tracing_actix_web::with_request_id;
+///
+/// async fn index() -> Result<HttpResponse, actix_web::Error> {
+///     let error = ErrorNotFound("The requested item does not exist");
+///     Err(with_request_id(error).into())
+/// }
+/// ```
+///
+/// [`TracingLogger`]: crate::TracingLogger
+pub fn with_request_id(error: impl Into<Error>) -> WithRequestId {
+    WithRequestId {
+        error: error.into(),
+        request_id: RequestId::current(),
+    }
+}
+
+/// An error wrapper that adds the [request id](RequestId) to the body of the error response.
+///
+/// The status code and the headers of the error response are the ones of the wrapped error,
+/// while the body is replaced by a JSON object:
+///
+/// ```json
+/// {
+///   "error": "<Display representation of the wrapped error>",
+///   "request_id": "<request id>"
+/// }
+/// ```
+///
+/// `request_id` is omitted if no request id was captured.
+///
+/// You can build a `WithRequestId` using [`with_request_id`] or [`WithRequestId::new`].
+#[derive(Debug)]
+pub struct WithRequestId {
+    error: Error,
+    request_id: Option<RequestId>,
+}
+
+impl WithRequestId {
+    /// Wrap `error`, including `request_id` in the body of the error response.
+    ///
+    /// ```rust
+    /// use actix_web::error::ErrorBadRequest;
+    /// use actix_web::HttpResponse;
+    /// use tracing_actix_web::{RequestId, WithRequestId};
+    ///
+    /// async fn index(request_id: RequestId) -> Result<HttpResponse, actix_web::Error> {
+    ///     let error = ErrorBadRequest("Missing `name`");
+    ///     Err(WithRequestId::new(error, request_id).into())
+    /// }
+    /// ```
+    pub fn new(error: impl Into<Error>, request_id: RequestId) -> Self {
+        Self {
+            error: error.into(),
+            request_id: Some(request_id),
+        }
+    }
+
+    /// The wrapped error.
+    pub fn inner(&self) -> &Error {
+        &self.error
+    }
+
+    /// Unwrap the wrapped error.
+    pub fn into_inner(self) -> Error {
+        self.error
+    }
+}
+
+impl std::fmt::Display for WithRequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl std::error::Error for WithRequestId {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl ResponseError for WithRequestId {
+    fn status_code(&self) -> StatusCode {
+        self.error.as_response_error().status_code()
+    }
+
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        let mut body = serde_json::Map::new();
+        body.insert("error".into(), self.error.to_string().into());
+        if let Some(request_id) = &self.request_id {
+            body.insert("request_id".into(), request_id.to_string().into());
+        }
+
+        let mut response = self
+            .error
+            .error_response()
+            .set_body(BoxBody::new(serde_json::Value::Object(body).to_string()));
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        response
+    }
+}
@@ -0,0 +1,155 @@
+//! The JSON access log.
+mod common;
+
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::error::ErrorForbidden;
+use actix_web::{test, web, App, HttpResponse};
+use common::{block_on, call, Collector};
+use serde_json::{json, Value};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use tracing_actix_web::{RootSpan, TracingLogger};
+
+/// An in-memory sink.
+#[derive(Clone, Default)]
+struct Lines(Arc<Mutex<Vec<u8>>>);
+
+impl Lines {
+    /// The lines written so far, parsed.
+    fn parsed(&self) -> Vec<Value> {
+        let lines = String::from_utf8(self.0.lock().unwrap().clone()).unwrap();
+        lines
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+}
+
+impl Write for Lines {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn lines_carry_the_values_recorded_on_the_root_span() {
+    async fn report(root_span: RootSpan) -> HttpResponse {
+        root_span.record("handler.memoized", false);
+        HttpResponse::Ok().body("Report")
+    }
+
+    let lines = Lines::default();
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let logger = TracingLogger::default()
+            .json_access_log(lines.clone())
+            .record_duration(true)
+            .record_bytes_written(true)
+            .record_outcome(true);
+        let app = App::new()
+            .wrap(logger)
+            .route("/reports/{id}", web::get().to(report));
+        let app = test::init_service(app).await;
+        let request = test::TestRequest::get().uri("/reports/42").to_request();
+        call(&app, request).await;
+    });
+
+    let lines = lines.parsed();
+    assert_eq!(lines.len(), 1);
+    let line = lines[0].as_object().unwrap();
+    let root_span = &collector.root_spans()[0];
+    for (name, value) in line {
+        match value {
+            // serde_json doesn't read floats back exactly.
+            Value::Number(number) if number.is_f64() => {
+                let expected = root_span.field(name).as_f64().unwrap();
+                assert!(
+                    (number.as_f64().unwrap() - expected).abs() < 1e-9,
+                    "{}",
+                    name
+                );
+            }
+            _ if name != "timestamp_ms" => assert_eq!(value, root_span.field(name), "{}", name),
+            _ => {}
+        }
+    }
+    assert_eq!(line["http.route"], "/reports/{id}");
+    assert_eq!(line["http.status_code"], 200);
+    assert_eq!(line["http.outcome"], "success");
+    assert_eq!(line["http.response.bytes_written"], 6);
+    assert_eq!(line["handler.memoized"], false);
+    assert!(line["http.server.duration_ms"].is_f64());
+    assert!(line["timestamp_ms"].is_u64());
+    // Fields that have not been recorded are left out
+    assert!(!line.contains_key("http.request.range"));
+}
+
+#[test]
+fn lines_are_written_once_the_body_has_been_sent() {
+    let lines = Lines::default();
+    let (_collector, _guard) = Collector::install();
+    block_on(async {
+        let logger = TracingLogger::default()
+            .json_access_log(lines.clone())
+            .record_bytes_written(true);
+        let app = App::new()
+            .wrap(logger)
+            .default_service(web::to(|| async { HttpResponse::Ok().body("Hello!") }));
+        let app = test::init_service(app).await;
+        let response = test::call_service(&app, test::TestRequest::get().to_request()).await;
+        assert!(lines.parsed().is_empty());
+        test::read_body(response).await;
+    });
+
+    assert_eq!(lines.parsed()[0]["http.response.bytes_written"], 6);
+}
+
+#[test]
+fn failed_requests_are_logged() {
+    let lines = Lines::default();
+    let (collector, _guard) = Collector::install();
+    let outcome = block_on(async {
+        let app = App::new()
+            .wrap_fn(|_req, _srv| async { Err::<ServiceResponse, _>(ErrorForbidden("Nope")) })
+            .wrap(TracingLogger::default().json_access_log(lines.clone()))
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        app.call(test::TestRequest::get().to_request()).await
+    });
+
+    assert!(outcome.is_err());
+    let line = &lines.parsed()[0];
+    assert_eq!(line["http.status_code"], 403);
+    assert_eq!(line["exception.message"], "Nope");
+    assert_eq!(
+        line["request_id"],
+        collector.root_spans()[0].field("request_id").clone()
+    );
+}
+
+#[test]
+fn requests_without_a_root_span_are_not_logged() {
+    let lines = Lines::default();
+    let (_collector, _guard) = Collector::install();
+    block_on(async {
+        let logger = TracingLogger::default()
+            .json_access_log(lines.clone())
+            .trace_if_body_over(1024);
+        let app = App::new()
+            .wrap(logger)
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        for payload in ["small", &"large".repeat(1024)] {
+            let request = test::TestRequest::post().set_payload(payload.to_owned());
+            call(&app, request.to_request()).await;
+        }
+    });
+
+    let lines = lines.parsed();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["http.method"], json!("POST"));
+}
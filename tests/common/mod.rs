@@ -0,0 +1,321 @@
+//! Test support: a `tracing` layer that collects the spans and events emitted while processing
+//! requests, with their fields.
+#![allow(dead_code)]
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::web::Bytes;
+use actix_web::{test, Error};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::DefaultGuard;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// The fields of a span or an event, keyed by name.
+pub type Fields = BTreeMap<String, Value>;
+
+/// A span, as seen by [`Collector`].
+#[derive(Clone, Debug)]
+pub struct SpanData {
+    pub name: &'static str,
+    /// The names of the fields declared by the span.
+    pub declared: Vec<&'static str>,
+    /// The fields recorded when the span was created.
+    pub initial_fields: Fields,
+    /// All the fields recorded on the span, at creation or afterwards.
+    pub fields: Fields,
+    /// How long the span stayed open, if it has been closed.
+    pub duration: Option<Duration>,
+}
+
+impl SpanData {
+    /// The value recorded for `name`, or `Value::Null` if nothing was recorded.
+    pub fn field(&self, name: &str) -> &Value {
+        self.fields.get(name).unwrap_or(&Value::Null)
+    }
+}
+
+/// An event, as seen by [`Collector`].
+#[derive(Clone, Debug)]
+pub struct EventData {
+    pub level: Level,
+    /// The names of the fields declared by the event.
+    pub declared: Vec<&'static str>,
+    pub fields: Fields,
+}
+
+impl EventData {
+    /// The value recorded for `name`, or `Value::Null` if nothing was recorded.
+    pub fn field(&self, name: &str) -> &Value {
+        self.fields.get(name).unwrap_or(&Value::Null)
+    }
+}
+
+/// Collects all the spans (in creation order) and events emitted while it is the default
+/// subscriber.
+#[derive(Clone, Default)]
+pub struct Collector {
+    spans: Arc<Mutex<Vec<SpanData>>>,
+    events: Arc<Mutex<Vec<EventData>>>,
+}
+
+impl Collector {
+    /// Install a new collector as the default subscriber for the current thread, until the
+    /// returned guard is dropped.
+    pub fn install() -> (Self, DefaultGuard) {
+        Self::install_with(tracing_subscriber::filter::LevelFilter::TRACE)
+    }
+
+    /// Like [`install`](Collector::install), filtering spans and events using `filter`.
+    pub fn install_with<F>(filter: F) -> (Self, DefaultGuard)
+    where
+        F: Layer<tracing_subscriber::Registry> + Send + Sync + 'static,
+    {
+        let collector = Self::default();
+        let subscriber = tracing_subscriber::registry()
+            .with(filter)
+            .with(collector.clone());
+        let guard = tracing::subscriber::set_default(subscriber);
+        (collector, guard)
+    }
+
+    /// The collector installed as the global default subscriber, installing it on first use.
+    ///
+    /// Use it to observe the requests served by an `HttpServer`, whose workers run on their own
+    /// threads. It is shared by all the tests of a test binary: tell your spans apart using
+    /// distinct paths.
+    pub fn global() -> &'static Self {
+        static GLOBAL: OnceLock<Collector> = OnceLock::new();
+        GLOBAL.get_or_init(|| {
+            let collector = Self::default();
+            let subscriber = tracing_subscriber::registry().with(collector.clone());
+            tracing::subscriber::set_global_default(subscriber).unwrap();
+            collector
+        })
+    }
+
+    /// The root spans of the requests for `target` (path and query), in creation order.
+    pub fn root_spans_for(&self, target: &str) -> Vec<SpanData> {
+        self.root_spans()
+            .into_iter()
+            .filter(|span| span.field("http.target") == target)
+            .collect()
+    }
+
+    /// The spans named `HTTP request`, i.e. the root spans, in creation order.
+    pub fn root_spans(&self) -> Vec<SpanData> {
+        self.spans
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|span| span.name == "HTTP request")
+            .cloned()
+            .collect()
+    }
+
+    /// The values recorded for `name` on each root span, in creation order.
+    pub fn root_span_field(&self, name: &str) -> Vec<Value> {
+        self.root_spans()
+            .iter()
+            .map(|span| span.field(name).clone())
+            .collect()
+    }
+
+    /// All the events, in emission order.
+    pub fn events(&self) -> Vec<EventData> {
+        self.events.lock().unwrap().clone()
+    }
+}
+
+struct SpanIndex(usize);
+
+struct SpanOpenedAt(Instant);
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Collector {
+    fn on_new_span(&self, attributes: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = FieldVisitor::default();
+        attributes.record(&mut fields);
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(SpanData {
+            name: attributes.metadata().name(),
+            declared: declared(attributes.metadata()),
+            initial_fields: fields.0.clone(),
+            fields: fields.0,
+            duration: None,
+        });
+        let span = ctx.span(id).unwrap();
+        let mut extensions = span.extensions_mut();
+        extensions.insert(SpanIndex(spans.len() - 1));
+        extensions.insert(SpanOpenedAt(Instant::now()));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let mut fields = FieldVisitor::default();
+        values.record(&mut fields);
+        let span = ctx.span(id).unwrap();
+        let index = span.extensions().get::<SpanIndex>().unwrap().0;
+        self.spans.lock().unwrap()[index].fields.extend(fields.0);
+    }
+
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        let mut fields = FieldVisitor::default();
+        event.record(&mut fields);
+        self.events.lock().unwrap().push(EventData {
+            level: *event.metadata().level(),
+            declared: declared(event.metadata()),
+            fields: fields.0,
+        });
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let span = ctx.span(&id).unwrap();
+        let extensions = span.extensions();
+        let index = extensions.get::<SpanIndex>().unwrap().0;
+        let opened_at = extensions.get::<SpanOpenedAt>().unwrap().0;
+        self.spans.lock().unwrap()[index].duration = Some(opened_at.elapsed());
+    }
+}
+
+fn declared(metadata: &tracing::Metadata<'_>) -> Vec<&'static str> {
+    metadata.fields().iter().map(|field| field.name()).collect()
+}
+
+#[derive(Default)]
+struct FieldVisitor(Fields);
+
+impl Visit for FieldVisitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_owned(), format!("{:?}", value).into());
+    }
+}
+
+/// Run `future` to completion on a new `actix-web` runtime.
+pub fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    actix_web::rt::System::new().block_on(future)
+}
+
+/// Send `request` to `app` and read the response body in full, so that the root span is closed
+/// when it returns.
+pub async fn call<S, R, B>(app: &S, request: R) -> (StatusCode, Bytes)
+where
+    S: Service<R, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    let response = test::call_service(app, request).await;
+    let status = response.status();
+    (status, test::read_body(response).await)
+}
+
+/// An `HttpServer` serving an application on a random local port, on its own thread.
+pub struct TestServer {
+    pub address: std::net::SocketAddr,
+    handle: actix_web::dev::ServerHandle,
+    thread: Option<std::thread::JoinHandle<std::io::Result<()>>>,
+}
+
+impl TestServer {
+    /// Start a single-worker `HttpServer` running an application wrapped in the `TracingLogger`
+    /// built by `logger`, with the routes registered by `configure`.
+    ///
+    /// `on_connect` is invoked on each new connection.
+    pub fn start<L>(
+        logger: L,
+        configure: fn(&mut actix_web::web::ServiceConfig),
+        on_connect: fn(&dyn std::any::Any, &mut actix_web::dev::Extensions),
+    ) -> Self
+    where
+        L: Fn() -> tracing_actix_web::TracingLogger<tracing_actix_web::DefaultRootSpanBuilder>
+            + Send
+            + Clone
+            + 'static,
+    {
+        let server = actix_web::HttpServer::new(move || {
+            actix_web::App::new().wrap(logger()).configure(configure)
+        })
+        .workers(1)
+        .on_connect(on_connect)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let address = server.addrs()[0];
+        let server = server.run();
+        let handle = server.handle();
+        let thread = std::thread::spawn(move || actix_web::rt::System::new().block_on(server));
+        Self {
+            address,
+            handle,
+            thread: Some(thread),
+        }
+    }
+
+    /// Send `requests` over a single connection, reading a response after each of them.
+    ///
+    /// Each request must be a complete HTTP/1.1 request; the response is read until the end of its
+    /// body, which must carry a `Content-Length`.
+    pub fn send(&self, requests: &[&[u8]]) -> Vec<String> {
+        use std::io::{BufRead, BufReader, Read, Write};
+
+        let stream = std::net::TcpStream::connect(self.address).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        let mut responses = Vec::new();
+        for request in requests {
+            writer.write_all(request).unwrap();
+            let mut response = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+                response.push_str(&line);
+                if line == "\r\n" || line.is_empty() {
+                    break;
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            response.push_str(std::str::from_utf8(&body).unwrap());
+            responses.push(response);
+        }
+        responses
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        actix_web::rt::System::new().block_on(self.handle.stop(true));
+        if let Some(thread) = self.thread.take() {
+            thread.join().unwrap().unwrap();
+        }
+    }
+}
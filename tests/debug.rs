@@ -0,0 +1,151 @@
+//! Debug mode, verbose clients and sensitive routes.
+mod common;
+
+use actix_web::http::header::HeaderName;
+use actix_web::{test, web, App, HttpResponse};
+use common::{block_on, call, Collector};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use tracing::Level;
+use tracing_actix_web::TracingLogger;
+use tracing_subscriber::EnvFilter;
+
+#[test]
+fn verbose_clients_can_be_selected_using_a_span_field_directive() {
+    let filter = EnvFilter::new("info,[{tracing_actix_web.verbose=true}]=debug");
+    let (collector, _guard) = Collector::install_with(filter);
+    block_on(async {
+        let verbose_clients = Arc::new(RwLock::new(HashSet::new()));
+        verbose_clients.write().unwrap().insert("acme".to_string());
+        let logger = TracingLogger::default()
+            .verbose_for(verbose_clients, HeaderName::from_static("x-client-id"));
+        let app = App::new().wrap(logger).default_service(web::to(|| async {
+            tracing::debug!("Cache miss");
+            HttpResponse::Ok().finish()
+        }));
+        let app = test::init_service(app).await;
+        for client_id in ["acme", "globex"] {
+            let request = test::TestRequest::default().insert_header(("X-Client-Id", client_id));
+            call(&app, request.to_request()).await;
+        }
+    });
+
+    assert_eq!(
+        collector.root_span_field("tracing_actix_web.verbose"),
+        vec![json!(true), Value::Null]
+    );
+    // Only the `DEBUG` event emitted while processing the request of `acme` got through
+    let debug_events = collector
+        .events()
+        .into_iter()
+        .filter(|event| event.level == Level::DEBUG)
+        .count();
+    assert_eq!(debug_events, 1);
+}
+
+#[test]
+fn verbose_clients_are_not_flagged_for_debugging() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let verbose_clients = Arc::new(RwLock::new(HashSet::new()));
+        verbose_clients.write().unwrap().insert("acme".to_string());
+        let logger = TracingLogger::default()
+            .verbose_for(verbose_clients, HeaderName::from_static("x-client-id"));
+        let app = App::new()
+            .wrap(logger)
+            .default_service(web::to(|body: String| async { body }));
+        let app = test::init_service(app).await;
+        let request = test::TestRequest::post()
+            .uri("/?page=2")
+            .insert_header(("X-Client-Id", "acme"))
+            .insert_header(("Range", "bytes=0-99"))
+            .set_payload("Hello!");
+        call(&app, request.to_request()).await;
+    });
+
+    let root_span = &collector.root_spans()[0];
+    assert_eq!(root_span.field("tracing_actix_web.verbose"), true);
+    for field in [
+        "http.request.headers",
+        "http.request.query",
+        "http.request.body_prefix",
+        "http.request.range",
+    ] {
+        assert!(root_span.field(field).is_null(), "{}", field);
+    }
+}
+
+#[test]
+fn debug_mode_is_ignored_on_sensitive_routes() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let logger = TracingLogger::default()
+            .debug_secret("my-secret")
+            .sensitive_route("/login");
+        let app = App::new()
+            .wrap(logger)
+            .route("/login", web::post().to(HttpResponse::Ok))
+            .route("/profile", web::get().to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        for request in [
+            test::TestRequest::post().uri("/login"),
+            test::TestRequest::get().uri("/profile"),
+        ] {
+            let request = request.insert_header(("X-Trace-Debug", "my-secret"));
+            call(&app, request.to_request()).await;
+        }
+    });
+
+    let headers = collector.root_span_field("http.request.headers");
+    assert!(headers[0].is_null());
+    assert!(headers[1].is_string());
+}
+
+#[test]
+fn debug_mode_captures_a_prefix_of_the_request_body() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default().debug_secret("my-secret"))
+            .route("/echo", web::post().to(|body: String| async { body }))
+            .route("/ignore", web::post().to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        for (uri, secret) in [
+            ("/echo", "my-secret"),
+            ("/echo", "guess"),
+            ("/ignore", "my-secret"),
+        ] {
+            let request = test::TestRequest::post()
+                .uri(uri)
+                .insert_header(("X-Trace-Debug", secret))
+                .set_payload("a".repeat(2048));
+            call(&app, request.to_request()).await;
+        }
+    });
+
+    assert_eq!(
+        collector.root_span_field("http.request.body_prefix"),
+        vec![json!("a".repeat(1024)), Value::Null, Value::Null]
+    );
+}
+
+#[test]
+fn the_query_string_of_sensitive_routes_is_not_recorded() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default().sensitive_route("/login"))
+            .route("/login", web::post().to(HttpResponse::Ok))
+            .route("/profile", web::post().to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        for uri in ["/login?token=secret", "/profile?tab=settings"] {
+            call(&app, test::TestRequest::post().uri(uri).to_request()).await;
+        }
+    });
+
+    assert_eq!(
+        collector.root_span_field("http.target"),
+        vec![json!("/login"), json!("/profile?tab=settings")]
+    );
+}
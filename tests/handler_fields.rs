@@ -0,0 +1,215 @@
+//! Fields of the root span recorded by handlers, middlewares and the error handling logic.
+mod common;
+
+use actix_web::dev::Service;
+use actix_web::{test, web, App, HttpMessage, HttpResponse};
+use common::{block_on, call, Collector};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tracing_actix_web::{IdempotencyOutcome, RootSpan, TracingLogger};
+
+#[test]
+fn handlers_can_record_pre_declared_fields() {
+    async fn upload(root_span: RootSpan) -> HttpResponse {
+        root_span.record("http.request.multipart_part_count", 3u64);
+        root_span.record("db.transaction", true);
+        HttpResponse::Ok().finish()
+    }
+
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default())
+            .route("/upload", web::post().to(upload))
+            .default_service(web::to(HttpResponse::NotFound));
+        let app = test::init_service(app).await;
+        for uri in ["/upload", "/balance"] {
+            call(&app, test::TestRequest::post().uri(uri).to_request()).await;
+        }
+    });
+
+    assert_eq!(
+        collector.root_span_field("http.request.multipart_part_count"),
+        vec![json!(3), Value::Null]
+    );
+    assert_eq!(
+        collector.root_span_field("db.transaction"),
+        vec![json!(true), Value::Null]
+    );
+}
+
+#[test]
+fn handlers_can_record_fields_on_a_lazy_root_span() {
+    async fn upload(root_span: RootSpan) -> HttpResponse {
+        root_span.record("http.request.multipart_part_count", 3u64);
+        root_span.record_flag("new-upload", "on");
+        // Lost: the root span has not been created yet.
+        tracing::Span::record(&root_span, "db.transaction", true);
+        HttpResponse::Ok().finish()
+    }
+
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default().lazy_root_span(true))
+            .route("/upload", web::post().to(upload));
+        let app = test::init_service(app).await;
+        call(&app, test::TestRequest::post().uri("/upload").to_request()).await;
+    });
+
+    let root_span = &collector.root_spans()[0];
+    assert_eq!(root_span.field("http.request.multipart_part_count"), 3);
+    assert_eq!(root_span.field("feature_flags"), "new-upload=on");
+    assert!(root_span.field("db.transaction").is_null());
+}
+
+#[test]
+fn middlewares_can_record_pre_declared_fields() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap_fn(|req, srv| {
+                if let Some(root_span) = req.extensions().get::<RootSpan>() {
+                    root_span.record("enduser.credential_id", "key-42");
+                }
+                srv.call(req)
+            })
+            .wrap(TracingLogger::default())
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        call(&app, test::TestRequest::get().to_request()).await;
+    });
+
+    assert_eq!(
+        collector.root_span_field("enduser.credential_id"),
+        vec![json!("key-42")]
+    );
+}
+
+#[test]
+fn memoized_handlers_are_told_apart() {
+    type Reports = Mutex<HashMap<u32, String>>;
+
+    async fn report(
+        root_span: RootSpan,
+        id: web::Path<u32>,
+        reports: web::Data<Reports>,
+    ) -> HttpResponse {
+        let id = id.into_inner();
+        if let Some(report) = reports.lock().unwrap().get(&id) {
+            root_span.record("handler.memoized", true);
+            return HttpResponse::Ok().body(report.clone());
+        }
+        root_span.record("handler.memoized", false);
+        let report = format!("Report #{}", id);
+        reports.lock().unwrap().insert(id, report.clone());
+        HttpResponse::Ok().body(report)
+    }
+
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default())
+            .app_data(web::Data::new(Reports::default()))
+            .route("/reports/{id}", web::get().to(report));
+        let app = test::init_service(app).await;
+        for _ in 0..2 {
+            let request = test::TestRequest::get().uri("/reports/42").to_request();
+            call(&app, request).await;
+        }
+    });
+
+    assert_eq!(
+        collector.root_span_field("handler.memoized"),
+        vec![json!(false), json!(true)]
+    );
+}
+
+#[test]
+fn idempotency_replays_are_recorded() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let seen_keys = Arc::new(Mutex::new(HashSet::new()));
+        let app = App::new()
+            .wrap_fn(move |req, srv| {
+                if let Some(key) = req.headers().get("Idempotency-Key") {
+                    let replayed = !seen_keys.lock().unwrap().insert(key.clone());
+                    IdempotencyOutcome::record(&req, replayed);
+                }
+                srv.call(req)
+            })
+            .wrap(TracingLogger::default().record_idempotency_key(true))
+            .route("/payments", web::post().to(HttpResponse::Created));
+        let app = test::init_service(app).await;
+        for _ in 0..2 {
+            let request = test::TestRequest::post()
+                .uri("/payments")
+                .insert_header(("Idempotency-Key", "8e03978e"));
+            call(&app, request.to_request()).await;
+        }
+    });
+
+    assert_eq!(
+        collector.root_span_field("http.idempotency.replayed"),
+        vec![json!(false), json!(true)]
+    );
+    assert_eq!(
+        collector.root_span_field("http.idempotency.key"),
+        vec![json!("8e03978e"), json!("8e03978e")]
+    );
+}
+
+#[test]
+fn idempotency_keys_are_not_recorded_on_sensitive_routes() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let logger = TracingLogger::default()
+            .record_idempotency_key(true)
+            .sensitive_route("/payments");
+        let app = App::new()
+            .wrap(logger)
+            .route("/payments", web::post().to(HttpResponse::Created));
+        let app = test::init_service(app).await;
+        let request = test::TestRequest::post()
+            .uri("/payments")
+            .insert_header(("Idempotency-Key", "8e03978e"));
+        call(&app, request.to_request()).await;
+    });
+
+    assert_eq!(
+        collector.root_span_field("http.idempotency.key"),
+        vec![Value::Null]
+    );
+}
+
+#[test]
+fn validation_errors_record_the_rejecting_extractor() {
+    async fn search(_query: web::Query<HashMap<String, u32>>) -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default())
+            .route("/search", web::get().to(search));
+        let app = test::init_service(app).await;
+        let request = test::TestRequest::get().uri("/search?page=last");
+        call(&app, request.to_request()).await;
+    });
+
+    // `page` has the wrong type: there is no offending field to record
+    assert_eq!(
+        collector.root_span_field("validation.extractor"),
+        vec![json!("Query")]
+    );
+    assert_eq!(
+        collector.root_span_field("validation.field"),
+        vec![Value::Null]
+    );
+    assert_eq!(
+        collector.root_span_field("http.status_code"),
+        vec![json!(400)]
+    );
+}
@@ -0,0 +1,336 @@
+//! Fields of the root span computed from the incoming request.
+mod common;
+
+use actix_web::http::Method;
+use actix_web::{test, web, App, HttpResponse};
+use common::{block_on, call, Collector};
+use serde_json::{json, Value};
+use tracing_actix_web::{ApiVersionSource, TracingLogger};
+
+#[test]
+fn api_version_is_read_from_the_accept_header() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let logger = TracingLogger::default()
+            .record_api_version(ApiVersionSource::AcceptHeader)
+            .default_api_version("v1");
+        let app = App::new()
+            .wrap(logger)
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        for accept in [
+            "application/vnd.acme.V2+json",
+            "application/json; version=3",
+            "*/*",
+        ] {
+            let request = test::TestRequest::default().insert_header(("Accept", accept));
+            call(&app, request.to_request()).await;
+        }
+    });
+
+    assert_eq!(
+        collector.root_span_field("http.api_version"),
+        vec![json!("v2"), json!("v3"), json!("v1")]
+    );
+}
+
+#[test]
+fn api_version_is_read_from_the_path_prefix() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let logger = TracingLogger::default().record_api_version(ApiVersionSource::PathPrefix);
+        let app = App::new()
+            .wrap(logger)
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        for uri in ["/v2/users", "/V2.1/users", "/users", "/v2beta/users"] {
+            call(&app, test::TestRequest::get().uri(uri).to_request()).await;
+        }
+    });
+
+    assert_eq!(
+        collector.root_span_field("http.api_version"),
+        vec![json!("v2"), json!("v2.1"), Value::Null, Value::Null]
+    );
+}
+
+#[test]
+fn non_standard_methods_are_normalized() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default().normalize_http_method(true))
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        for method in [Method::GET, Method::from_bytes(b"PURGE").unwrap()] {
+            call(
+                &app,
+                test::TestRequest::default().method(method).to_request(),
+            )
+            .await;
+        }
+    });
+
+    assert_eq!(
+        collector.root_span_field("http.method"),
+        vec![json!("GET"), json!("_OTHER")]
+    );
+    assert_eq!(
+        collector.root_span_field("http.method_raw"),
+        vec![Value::Null, json!("PURGE")]
+    );
+}
+
+#[test]
+fn standard_methods_are_not_normalized() {
+    let methods = [
+        Method::GET,
+        Method::HEAD,
+        Method::POST,
+        Method::PUT,
+        Method::DELETE,
+        Method::CONNECT,
+        Method::OPTIONS,
+        Method::TRACE,
+        Method::PATCH,
+    ];
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default().normalize_http_method(true))
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        for method in methods.clone() {
+            call(
+                &app,
+                test::TestRequest::default().method(method).to_request(),
+            )
+            .await;
+        }
+    });
+
+    let expected: Vec<_> = methods
+        .iter()
+        .map(|method| json!(method.as_str()))
+        .collect();
+    assert_eq!(collector.root_span_field("http.method"), expected);
+    assert!(collector
+        .root_span_field("http.method_raw")
+        .iter()
+        .all(Value::is_null));
+}
+
+#[test]
+fn non_standard_methods_are_recorded_as_is_by_default() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default())
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        let method = Method::from_bytes(b"PURGE").unwrap();
+        call(
+            &app,
+            test::TestRequest::default().method(method).to_request(),
+        )
+        .await;
+    });
+
+    assert_eq!(
+        collector.root_span_field("http.method"),
+        vec![json!("PURGE")]
+    );
+    assert_eq!(
+        collector.root_span_field("http.method_raw"),
+        vec![Value::Null]
+    );
+}
+
+#[test]
+fn transfer_encoding_is_recorded_for_chunked_requests() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default().record_transfer_encoding(true))
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        let chunked = test::TestRequest::post().insert_header(("Transfer-Encoding", "chunked"));
+        call(&app, chunked.to_request()).await;
+        let sized = test::TestRequest::post().set_payload("Hello!");
+        call(&app, sized.to_request()).await;
+    });
+
+    assert_eq!(
+        collector.root_span_field("http.request.transfer_encoding"),
+        vec![json!("chunked"), Value::Null]
+    );
+}
+
+#[test]
+fn transfer_encoding_is_recorded_as_is() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default().record_transfer_encoding(true))
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        let request =
+            test::TestRequest::post().insert_header(("Transfer-Encoding", "gzip, chunked"));
+        call(&app, request.to_request()).await;
+    });
+
+    assert_eq!(
+        collector.root_span_field("http.request.transfer_encoding"),
+        vec![json!("gzip, chunked")]
+    );
+}
+
+#[test]
+fn transfer_encoding_is_not_recorded_by_default() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default())
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        let request = test::TestRequest::post().insert_header(("Transfer-Encoding", "chunked"));
+        call(&app, request.to_request()).await;
+    });
+
+    assert_eq!(
+        collector.root_span_field("http.request.transfer_encoding"),
+        vec![Value::Null]
+    );
+}
+
+#[test]
+fn max_forwards_is_recorded() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default().record_max_forwards(true))
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        for max_forwards in ["3", "0", "-1", "many", "99999999999999999999"] {
+            let request = test::TestRequest::default()
+                .method(Method::TRACE)
+                .insert_header(("Max-Forwards", max_forwards));
+            call(&app, request.to_request()).await;
+        }
+        let request = test::TestRequest::default().method(Method::OPTIONS);
+        call(&app, request.to_request()).await;
+    });
+
+    // Malformed values and missing headers are not recorded
+    assert_eq!(
+        collector.root_span_field("http.request.max_forwards"),
+        vec![
+            json!(3),
+            json!(0),
+            Value::Null,
+            Value::Null,
+            Value::Null,
+            Value::Null
+        ]
+    );
+}
+
+#[test]
+fn max_forwards_is_not_recorded_by_default() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default())
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        let request = test::TestRequest::default()
+            .method(Method::TRACE)
+            .insert_header(("Max-Forwards", "3"));
+        call(&app, request.to_request()).await;
+    });
+
+    assert_eq!(
+        collector.root_span_field("http.request.max_forwards"),
+        vec![Value::Null]
+    );
+}
+
+#[test]
+fn channel_is_classified() {
+    fn channel(request: &actix_web::dev::ServiceRequest) -> Option<&'static str> {
+        let header = |name| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        let user_agent = header("User-Agent").unwrap_or("").to_ascii_lowercase();
+        if user_agent.contains("bot") || user_agent.contains("crawler") {
+            return Some("bot");
+        }
+        match header("X-Client-Platform") {
+            Some("ios") | Some("android") => Some("mobile"),
+            _ if request.headers().contains_key("X-Api-Key") => Some("api"),
+            _ if user_agent.starts_with("mozilla/") => Some("web"),
+            _ => None,
+        }
+    }
+
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default().classify_channel(channel))
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        let requests = [
+            ("User-Agent", "Mozilla/5.0 (X11; Linux x86_64)"),
+            ("X-Client-Platform", "ios"),
+            ("X-Api-Key", "a1b2c3"),
+            ("User-Agent", "Googlebot/2.1"),
+            ("User-Agent", "curl/8.0.1"),
+        ];
+        for header in requests {
+            let request = test::TestRequest::get().insert_header(header);
+            call(&app, request.to_request()).await;
+        }
+    });
+
+    assert_eq!(
+        collector.root_span_field("client.channel"),
+        vec![
+            json!("web"),
+            json!("mobile"),
+            json!("api"),
+            json!("bot"),
+            Value::Null
+        ]
+    );
+}
+
+#[test]
+fn host_is_checked_against_the_allowed_hosts() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let logger = TracingLogger::default()
+            .allowed_host("example.com")
+            .allowed_host("*.example.com");
+        let app = App::new()
+            .wrap(logger)
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        for host in [
+            "example.com",
+            "API.example.com:8443",
+            "example.com.evil.io",
+            "evilexample.com",
+        ] {
+            let request = test::TestRequest::get().insert_header(("Host", host));
+            call(&app, request.to_request()).await;
+        }
+    });
+
+    assert_eq!(
+        collector.root_span_field("http.host.allowed"),
+        vec![json!(true), json!(true), json!(false), json!(false)]
+    );
+}
@@ -0,0 +1,153 @@
+//! The request id, as seen by handlers and error responses.
+mod common;
+
+use actix_web::error::{ErrorBadRequest, ErrorNotFound};
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App, HttpResponse, ResponseError};
+use common::{block_on, call, Collector};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing_actix_web::{with_request_id, RequestId, TracingLogger, WithRequestId};
+use uuid::Uuid;
+
+#[test]
+fn error_responses_include_the_request_id_of_the_in_flight_request() {
+    async fn missing() -> Result<HttpResponse, actix_web::Error> {
+        // The request id must survive the handler yielding to the runtime
+        actix_web::rt::time::sleep(Duration::from_millis(1)).await;
+        Err(with_request_id(ErrorNotFound("The requested item does not exist")).into())
+    }
+
+    let (collector, _guard) = Collector::install();
+    let (status, content_type, body) = block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default())
+            .route("/missing", web::get().to(missing));
+        let app = test::init_service(app).await;
+        let request = test::TestRequest::get().uri("/missing").to_request();
+        let response = test::call_service(&app, request).await;
+        let content_type = response.headers().get(CONTENT_TYPE).cloned();
+        let status = response.status();
+        (status, content_type, test::read_body(response).await)
+    });
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(content_type.unwrap(), "application/json");
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body,
+        json!({
+            "error": "The requested item does not exist",
+            "request_id": collector.root_span_field("request_id")[0],
+        })
+    );
+}
+
+#[test]
+fn error_responses_include_an_explicit_request_id() {
+    async fn invalid(request_id: RequestId) -> Result<HttpResponse, actix_web::Error> {
+        Err(WithRequestId::new(ErrorBadRequest("Missing `name`"), request_id).into())
+    }
+
+    let (collector, _guard) = Collector::install();
+    let (status, body) = block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default().request_id_prefix("svcA-").unwrap())
+            .route("/invalid", web::get().to(invalid));
+        let app = test::init_service(app).await;
+        call(&app, test::TestRequest::get().uri("/invalid").to_request()).await
+    });
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let request_id = collector.root_span_field("request_id").remove(0);
+    assert!(request_id.as_str().unwrap().starts_with("svcA-"));
+    assert_eq!(body["request_id"], request_id);
+}
+
+#[test]
+fn the_request_id_is_cleared_once_the_request_has_been_processed() {
+    let captured = block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default())
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        call(&app, test::TestRequest::get().to_request()).await;
+        with_request_id(ErrorNotFound("Not found"))
+    });
+
+    let body = json_body(captured.error_response());
+    assert_eq!(body, json!({ "error": "Not found" }));
+}
+
+#[test]
+fn the_request_id_is_not_captured_outside_of_a_request() {
+    let error = with_request_id(ErrorNotFound("Not found"));
+
+    assert_eq!(error.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(error.to_string(), "Not found");
+    let body = json_body(error.error_response());
+    assert_eq!(body, json!({ "error": "Not found" }));
+}
+
+fn json_body(response: HttpResponse) -> Value {
+    let body = block_on(actix_web::body::to_bytes(response.into_body())).unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[test]
+fn the_prefix_is_only_included_in_the_display_representation() {
+    async fn index(request_id: RequestId) -> String {
+        let uuid: Uuid = request_id.clone().into();
+        assert_eq!(*request_id, uuid);
+        format!("{} {}", request_id, uuid)
+    }
+
+    let (collector, _guard) = Collector::install();
+    let (_, body) = block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default().request_id_prefix("svcA-").unwrap())
+            .default_service(web::to(index));
+        let app = test::init_service(app).await;
+        call(&app, test::TestRequest::get().to_request()).await
+    });
+
+    let body = std::str::from_utf8(&body).unwrap();
+    let (displayed, uuid) = body.split_once(' ').unwrap();
+    assert_eq!(displayed, format!("svcA-{}", uuid));
+    assert!(Uuid::parse_str(uuid).is_ok());
+    assert_eq!(
+        collector.root_span_field("request_id"),
+        vec![json!(displayed)]
+    );
+}
+
+#[test]
+fn prefixes_can_be_computed_at_runtime() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let prefix = format!("{}-", std::process::id());
+        let app = App::new()
+            .wrap(TracingLogger::default().request_id_prefix(prefix).unwrap())
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        call(&app, test::TestRequest::get().to_request()).await;
+    });
+
+    let request_id = collector.root_span_field("request_id").remove(0);
+    let prefix = format!("{}-", std::process::id());
+    assert!(request_id.as_str().unwrap().starts_with(&prefix));
+}
+
+#[test]
+fn prefixes_with_non_printable_characters_are_rejected() {
+    for prefix in ["svc A-", "svc\n", "svcé-"] {
+        let error = match TracingLogger::default().request_id_prefix(prefix) {
+            Ok(_) => panic!("{:?} was accepted", prefix),
+            Err(error) => error,
+        };
+        assert_eq!(error.prefix(), prefix);
+    }
+    assert!(TracingLogger::default().request_id_prefix("").is_ok());
+}
@@ -0,0 +1,119 @@
+//! Fields of the root span recorded when the response is ready.
+mod common;
+
+use actix_web::{test, web, App, HttpResponse};
+use common::{block_on, call, Collector};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing_actix_web::TracingLogger;
+
+#[test]
+fn duration_matches_the_duration_of_the_root_span() {
+    async fn slow() -> HttpResponse {
+        actix_web::rt::time::sleep(Duration::from_millis(20)).await;
+        HttpResponse::Ok().body("Hello!")
+    }
+
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default().record_duration(true))
+            .route("/", web::get().to(slow));
+        let app = test::init_service(app).await;
+        call(&app, test::TestRequest::get().to_request()).await;
+    });
+
+    let root_span = &collector.root_spans()[0];
+    let duration_ms = root_span.field("http.server.duration_ms").as_f64().unwrap();
+    let span_duration_ms = root_span.duration.unwrap().as_secs_f64() * 1000.0;
+    assert!(duration_ms >= 20.0);
+    assert!((span_duration_ms - duration_ms).abs() < 5.0);
+}
+
+#[test]
+fn server_timing_headers_are_joined() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default().record_server_timing(true))
+            .route(
+                "/timed",
+                web::to(|| async {
+                    HttpResponse::Ok()
+                        .append_header(("Server-Timing", r#"db;dur=42;desc="query""#))
+                        .append_header(("Server-Timing", "cache;dur=1"))
+                        .finish()
+                }),
+            )
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        for uri in ["/timed", "/untimed"] {
+            call(&app, test::TestRequest::with_uri(uri).to_request()).await;
+        }
+    });
+
+    assert_eq!(
+        collector.root_span_field("http.response.server_timing"),
+        vec![json!(r#"db;dur=42;desc="query", cache;dur=1"#), Value::Null]
+    );
+}
+
+#[test]
+fn root_span_outlives_duration_while_the_body_is_streamed() {
+    use actix_web::rt::time::{sleep, Sleep};
+    use actix_web::web::Bytes;
+    use futures_core::Stream;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    /// Yields two chunks, 20ms apart.
+    struct SlowBody {
+        delay: Option<Pin<Box<Sleep>>>,
+        remaining: usize,
+    }
+
+    impl Stream for SlowBody {
+        type Item = Result<Bytes, actix_web::Error>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            if self.remaining == 0 {
+                return Poll::Ready(None);
+            }
+            let delay = self
+                .delay
+                .get_or_insert_with(|| Box::pin(sleep(Duration::from_millis(20))));
+            if delay.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            self.delay = None;
+            self.remaining -= 1;
+            Poll::Ready(Some(Ok(Bytes::from_static(b"chunk"))))
+        }
+    }
+
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::default().record_duration(true))
+            .route(
+                "/",
+                web::get().to(|| async {
+                    HttpResponse::Ok().streaming(SlowBody {
+                        delay: None,
+                        remaining: 2,
+                    })
+                }),
+            );
+        let app = test::init_service(app).await;
+        let (_, body) = call(&app, test::TestRequest::get().to_request()).await;
+        assert_eq!(body, "chunkchunk");
+    });
+
+    let root_span = &collector.root_spans()[0];
+    let duration_ms = root_span.field("http.server.duration_ms").as_f64().unwrap();
+    let span_duration_ms = root_span.duration.unwrap().as_secs_f64() * 1000.0;
+    // `http.server.duration_ms` stops when the response is ready, the span when the body is done
+    assert!(duration_ms < 20.0);
+    assert!(span_duration_ms >= duration_ms + 40.0);
+}
@@ -0,0 +1,129 @@
+//! Requests served over a real connection by an `HttpServer`.
+mod common;
+
+use actix_web::{web, HttpResponse};
+use common::{Collector, TestServer};
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+use tracing_actix_web::{ConnectionMetadata, TracingLogger};
+
+fn echo(config: &mut web::ServiceConfig) {
+    config.default_service(web::to(|body: String| async move {
+        HttpResponse::Ok().body(body)
+    }));
+}
+
+#[test]
+fn transfer_encoding_is_recorded_for_chunked_uploads() {
+    let collector = Collector::global();
+    let server = TestServer::start(
+        || TracingLogger::default().record_transfer_encoding(true),
+        echo,
+        tracing_actix_web::on_connect,
+    );
+    let responses = server.send(&[
+        b"POST /chunked HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+          6\r\nHello!\r\n0\r\n\r\n",
+        b"POST /chunked HTTP/1.1\r\nHost: localhost\r\nContent-Length: 6\r\n\r\nHello!",
+    ]);
+    drop(server);
+
+    for response in responses {
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(response.ends_with("Hello!"), "{}", response);
+    }
+    let transfer_encodings: Vec<_> = collector
+        .root_spans_for("/chunked")
+        .iter()
+        .map(|span| span.field("http.request.transfer_encoding").clone())
+        .collect();
+    assert_eq!(transfer_encodings, vec![json!("chunked"), Value::Null]);
+}
+
+#[test]
+fn time_to_first_request_is_only_recorded_for_the_first_request_on_a_connection() {
+    let collector = Collector::global();
+    let server = TestServer::start(TracingLogger::default, echo, tracing_actix_web::on_connect);
+    let request: &[u8] = b"GET /first HTTP/1.1\r\nHost: localhost\r\n\r\n";
+    server.send(&[request, request]);
+    drop(server);
+
+    let root_spans = collector.root_spans_for("/first");
+    assert_eq!(root_spans.len(), 2);
+    assert!(root_spans[0].field("net.time_to_first_request_ms").is_f64());
+    assert_eq!(root_spans[0].field("net.connection.reused"), &json!(false));
+    assert!(root_spans[1]
+        .field("net.time_to_first_request_ms")
+        .is_null());
+    assert_eq!(root_spans[1].field("net.connection.reused"), &json!(true));
+}
+
+#[test]
+fn connection_fields_are_recorded_on_lazy_root_spans() {
+    let collector = Collector::global();
+    let server = TestServer::start(
+        || TracingLogger::default().lazy_root_span(true),
+        echo,
+        tracing_actix_web::on_connect,
+    );
+    let request: &[u8] = b"GET /lazy HTTP/1.1\r\nHost: localhost\r\n\r\n";
+    server.send(&[request, request]);
+    drop(server);
+
+    let root_spans = collector.root_spans_for("/lazy");
+    assert_eq!(root_spans.len(), 2);
+    assert!(root_spans[0].field("net.time_to_first_request_ms").is_f64());
+    assert_eq!(root_spans[0].field("net.connection.reused"), &json!(false));
+    assert!(root_spans[1]
+        .field("net.time_to_first_request_ms")
+        .is_null());
+    assert_eq!(root_spans[1].field("net.connection.reused"), &json!(true));
+}
+
+#[test]
+fn full_duration_is_only_recorded_for_the_first_request_on_a_connection() {
+    fn on_connect(connection: &dyn std::any::Any, data: &mut actix_web::dev::Extensions) {
+        tracing_actix_web::on_connect(connection, data);
+        let metadata = data.get_mut::<ConnectionMetadata>().unwrap();
+        metadata.set_accepted_at(Instant::now() - Duration::from_millis(50));
+    }
+
+    let collector = Collector::global();
+    let server = TestServer::start(
+        || TracingLogger::default().record_full_duration(true),
+        echo,
+        on_connect,
+    );
+    let request: &[u8] = b"GET /full HTTP/1.1\r\nHost: localhost\r\n\r\n";
+    server.send(&[request, request]);
+    drop(server);
+
+    let root_spans = collector.root_spans_for("/full");
+    assert_eq!(root_spans.len(), 2);
+    let full_duration_ms = root_spans[0]
+        .field("net.full_duration_ms")
+        .as_f64()
+        .unwrap();
+    assert!(full_duration_ms >= 50.0);
+    assert!(root_spans[1].field("net.full_duration_ms").is_null());
+}
+
+#[test]
+fn full_duration_defaults_to_the_on_connect_timestamp() {
+    let collector = Collector::global();
+    let server = TestServer::start(
+        || TracingLogger::default().record_full_duration(true),
+        echo,
+        tracing_actix_web::on_connect,
+    );
+    server.send(&[b"GET /full-default HTTP/1.1\r\nHost: localhost\r\n\r\n"]);
+    drop(server);
+
+    let root_spans = collector.root_spans_for("/full-default");
+    assert_eq!(root_spans.len(), 1);
+    let full_duration_ms = root_spans[0]
+        .field("net.full_duration_ms")
+        .as_f64()
+        .unwrap();
+    assert!(full_duration_ms >= 0.0);
+}
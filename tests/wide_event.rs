@@ -0,0 +1,114 @@
+//! The event emitted by `WideEventRootSpanBuilder`.
+mod common;
+
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::error::ErrorForbidden;
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App, HttpResponse};
+use common::{block_on, call, Collector, EventData};
+use serde_json::json;
+use std::collections::BTreeSet;
+use tracing_actix_web::{RootSpan, TracingLogger, WideEventRootSpanBuilder};
+
+/// The events emitted by `WideEventRootSpanBuilder`.
+fn wide_events(collector: &Collector) -> Vec<EventData> {
+    collector
+        .events()
+        .into_iter()
+        .filter(|event| event.field("message") == "HTTP request")
+        .collect()
+}
+
+#[test]
+fn the_wide_event_declares_all_the_fields_of_the_root_span() {
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let app = App::new()
+            .wrap(TracingLogger::<WideEventRootSpanBuilder>::new())
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        call(&app, test::TestRequest::get().to_request()).await;
+    });
+
+    let root_span: BTreeSet<_> = collector.root_spans()[0].declared.iter().copied().collect();
+    let event: BTreeSet<_> = wide_events(&collector)[0]
+        .declared
+        .iter()
+        .copied()
+        .filter(|name| *name != "message")
+        .collect();
+    assert_eq!(root_span, event);
+}
+
+#[test]
+fn the_wide_event_carries_the_values_recorded_on_the_root_span() {
+    async fn report(root_span: RootSpan) -> HttpResponse {
+        root_span.record("handler.memoized", true);
+        HttpResponse::Ok().body("Report")
+    }
+
+    let (collector, _guard) = Collector::install();
+    block_on(async {
+        let logger = TracingLogger::<WideEventRootSpanBuilder>::new()
+            .record_bytes_written(true)
+            .record_outcome(true);
+        let app = App::new()
+            .wrap(logger)
+            .route("/reports/{id}", web::get().to(report));
+        let app = test::init_service(app).await;
+        let request = test::TestRequest::get().uri("/reports/42").to_request();
+        call(&app, request).await;
+    });
+
+    let root_span = &collector.root_spans()[0];
+    let events = wide_events(&collector);
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    for name in [
+        "http.method",
+        "http.route",
+        "http.target",
+        "http.status_code",
+        "request_id",
+        "otel.kind",
+        "otel.status_code",
+        "http.outcome",
+        "http.response.bytes_written",
+        "handler.memoized",
+    ] {
+        assert_eq!(event.field(name), root_span.field(name), "{}", name);
+    }
+    assert_eq!(event.field("http.route"), "/reports/{id}");
+    assert_eq!(event.field("http.response.bytes_written"), 6);
+    assert_eq!(event.field("handler.memoized"), true);
+}
+
+#[test]
+fn the_wide_event_is_emitted_for_requests_that_failed() {
+    let (collector, _guard) = Collector::install();
+    let outcome = block_on(async {
+        let app = App::new()
+            .wrap_fn(|_req, _srv| async { Err::<ServiceResponse, _>(ErrorForbidden("Nope")) })
+            .wrap(TracingLogger::<WideEventRootSpanBuilder>::new().record_duration(true))
+            .default_service(web::to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        app.call(test::TestRequest::get().to_request()).await
+    });
+
+    assert!(outcome.is_err());
+    let root_span = &collector.root_spans()[0];
+    let events = wide_events(&collector);
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(
+        event.field("http.status_code"),
+        &json!(StatusCode::FORBIDDEN.as_u16())
+    );
+    assert_eq!(event.field("exception.message"), "Nope");
+    assert_eq!(event.field("request_id"), root_span.field("request_id"));
+    assert_eq!(
+        event.field("http.server.duration_ms"),
+        root_span.field("http.server.duration_ms")
+    );
+    assert!(event.field("http.server.duration_ms").is_f64());
+}